@@ -3,22 +3,67 @@ use std::collections::HashMap;
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 
+fn is_false(value: &bool) -> bool {
+  !value
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SourceLocation {
+  pub file: String,
+  pub line: usize
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashValue {
+  pub signed:   i32,
+  pub unsigned: u32
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NativeParam {
   #[serde(rename = "type")]
-  pub ty:      String,
-  pub name:    String,
+  pub ty:           String,
+  pub name:         String,
   #[serde(skip_serializing_if = "Option::is_none")]
-  pub default: Option<String>
+  pub default:      Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub default_hash: Option<HashValue>
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NativeUsage {
+  pub function: String,
+  pub file:     String,
+  pub line:     usize
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Native {
-  pub name:        String,
+  pub name:           String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub sch_comment:    Option<String>,
+  pub params:         Vec<NativeParam>,
+  pub return_type:    String,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub configurations: Vec<String>,
   #[serde(skip_serializing_if = "Option::is_none")]
-  pub sch_comment: Option<String>,
-  pub params:      Vec<NativeParam>,
-  pub return_type: String
+  pub source:         Option<SourceLocation>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub usages:         Vec<NativeUsage>,
+  #[serde(default, skip_serializing_if = "is_false")]
+  pub is_debug_only:  bool
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScriptFunction {
+  pub name:          String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub comment:       Option<String>,
+  pub params:        Vec<NativeParam>,
+  pub return_type:   String,
+  pub source:        SourceLocation,
+  #[serde(default, skip_serializing_if = "is_false")]
+  pub is_debug_only: bool
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -27,12 +72,24 @@ pub struct Namespace {
   pub natives: HashMap<u64, Native>
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(untagged)]
+pub enum ResolvedValue {
+  Int(i64),
+  Float(f32),
+  Bool(bool)
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EnumValue {
   #[serde(skip_serializing_if = "Option::is_none")]
-  pub comment: Option<String>,
+  pub comment:        Option<String>,
   #[serde(skip_serializing_if = "Option::is_none")]
-  pub value:   Option<String>
+  pub value:          Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub resolved_value: Option<ResolvedValue>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub hash:           Option<HashValue>
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -46,38 +103,106 @@ pub struct StructField {
   pub default_value: Option<String>
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EnumKind {
+  #[default]
+  Enum,
+  StrictEnum,
+  HashEnum
+}
+
+impl EnumKind {
+  pub fn underlying_type(self) -> &'static str {
+    match self {
+      EnumKind::Enum | EnumKind::StrictEnum => "int32_t",
+      EnumKind::HashEnum => "uint32_t"
+    }
+  }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
 pub enum TypeDefinition {
   Enum {
     #[serde(skip_serializing_if = "Option::is_none")]
     comment: Option<String>,
-    values:  IndexMap<String, EnumValue>
+    #[serde(default)]
+    kind:    EnumKind,
+    values:  IndexMap<String, EnumValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source:  Option<SourceLocation>
   },
   Struct {
     #[serde(skip_serializing_if = "Option::is_none")]
     comment: Option<String>,
-    fields:  IndexMap<String, StructField>
+    fields:  IndexMap<String, StructField>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source:  Option<SourceLocation>
   },
   NativeType {
     #[serde(skip_serializing_if = "Option::is_none")]
     comment:   Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    alias_for: Option<String>
+    alias_for: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source:    Option<SourceLocation>
+  },
+  FunctionPointer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment:     Option<String>,
+    return_type: String,
+    params:      Vec<NativeParam>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source:      Option<SourceLocation>
   }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ConstDefinition {
   #[serde(skip_serializing_if = "Option::is_none")]
-  pub comment:   Option<String>,
-  pub type_name: String,
-  pub value:     String
+  pub comment:        Option<String>,
+  pub type_name:      String,
+  pub value:          String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub resolved_value: Option<ResolvedValue>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub hash:           Option<HashValue>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub source:         Option<SourceLocation>
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum GlobalDefinition {
+  Variable {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment:       Option<String>,
+    type_name:     String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    array_size:    Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    default_value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source:        Option<SourceLocation>
+  },
+  Tweak {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment:   Option<String>,
+    type_name: String,
+    value:     String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source:    Option<SourceLocation>
+  }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DocumentRoot {
   pub types:     IndexMap<String, TypeDefinition>,
   pub constants: IndexMap<String, ConstDefinition>,
-  pub natives:   IndexMap<String, Native>
+  #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+  pub globals:   IndexMap<String, GlobalDefinition>,
+  pub natives:   IndexMap<String, Native>,
+  #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+  pub functions: IndexMap<String, ScriptFunction>
 }
@@ -0,0 +1,28 @@
+use std::fmt::Display;
+
+use crate::parser::model::Span;
+
+/// A problem found in otherwise parseable input.
+#[derive(Debug)]
+pub struct Diagnostic {
+  pub span:    Option<Span>,
+  pub message: String
+}
+
+impl Diagnostic {
+  pub fn new(span: &Span, message: impl Into<String>) -> Self {
+    Self {
+      span:    Some(span.clone()),
+      message: message.into()
+    }
+  }
+}
+
+impl Display for Diagnostic {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match &self.span {
+      Some(span) => write!(f, "{}:{}: {}", span.file, span.start, self.message),
+      None => write!(f, "{}", self.message)
+    }
+  }
+}
@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+
+use indexmap::IndexMap;
+
+use crate::{
+  diagnostic::Diagnostic,
+  joaat::joaat,
+  parser::model::{Declaration, Expression, Literal, Span}
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+  Int(i64),
+  Float(f32),
+  Bool(bool)
+}
+
+impl Value {
+  fn as_float(self) -> Option<f32> {
+    match self {
+      Value::Int(int) => Some(int as f32),
+      Value::Float(float) => Some(float),
+      Value::Bool(_) => None
+    }
+  }
+
+  fn as_bool(self) -> Option<bool> {
+    match self {
+      Value::Int(int) => Some(int != 0),
+      Value::Bool(b) => Some(b),
+      Value::Float(_) => None
+    }
+  }
+}
+
+enum Error {
+  /// A referenced name failed to resolve, the problem has been reported there.
+  Unresolved,
+  /// The expression can only be evaluated by the game, e.g. `SIZE_OF(X)`.
+  Unsupported,
+  /// The chain of names leading back to the first one.
+  Cycle(Vec<String>),
+  Invalid(String)
+}
+
+#[derive(Clone, Copy)]
+enum Definition<'a> {
+  Expression(&'a Expression),
+  /// An enum member without a value, one more than the previous member or `0`
+  /// for the first one.
+  Next(Option<&'a str>)
+}
+
+struct Symbol<'a> {
+  definition: Definition<'a>,
+  span:       &'a Span
+}
+
+struct Evaluator<'a> {
+  symbols: IndexMap<&'a str, Symbol<'a>>,
+  values:  HashMap<&'a str, Option<Value>>,
+  stack:   Vec<&'a str>
+}
+
+/// Resolves the constants and enum members of all files to numbers where
+/// possible. Unknown names, cycles and invalid arithmetic are reported as
+/// diagnostics.
+pub fn resolve_values(
+  declarations: &[Declaration],
+  diagnostics: &mut Vec<Diagnostic>
+) -> HashMap<String, Value> {
+  let mut evaluator = Evaluator {
+    symbols: IndexMap::new(),
+    values:  HashMap::new(),
+    stack:   vec![]
+  };
+
+  for decl in declarations {
+    match decl {
+      Declaration::Const(const_decl) => {
+        evaluator.define(
+          &const_decl.name,
+          Definition::Expression(&const_decl.value),
+          &const_decl.span
+        )
+      }
+      Declaration::Enum(enum_decl) => {
+        let mut previous = None;
+
+        for field in &enum_decl.values {
+          let definition = match &field.value {
+            Some(value) => Definition::Expression(value),
+            None => Definition::Next(previous)
+          };
+
+          evaluator.define(&field.name, definition, &field.span);
+          previous = Some(field.name.as_str());
+        }
+      }
+      _ => {}
+    }
+  }
+
+  let names = evaluator.symbols.keys().copied().collect::<Vec<_>>();
+  for name in names {
+    let _ = evaluator.resolve(name, diagnostics);
+  }
+
+  evaluator
+    .values
+    .into_iter()
+    .filter_map(|(name, value)| value.map(|value| (name.to_owned(), value)))
+    .collect()
+}
+
+impl<'a> Evaluator<'a> {
+  fn define(&mut self, name: &'a str, definition: Definition<'a>, span: &'a Span) {
+    self
+      .symbols
+      .entry(name)
+      .or_insert(Symbol { definition, span });
+  }
+
+  fn resolve(&mut self, name: &'a str, diagnostics: &mut Vec<Diagnostic>) -> Result<Value, Error> {
+    if let Some(value) = self.values.get(name) {
+      return value.ok_or(Error::Unresolved);
+    }
+
+    let Some(symbol) = self.symbols.get(name) else {
+      return Err(Error::Invalid(format!("unknown identifier `{name}`")));
+    };
+
+    if let Some(index) = self.stack.iter().position(|n| *n == name) {
+      let mut chain = self.stack[index..]
+        .iter()
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>();
+      chain.push(name.to_owned());
+
+      return Err(Error::Cycle(chain));
+    }
+
+    let span = symbol.span;
+    let definition = symbol.definition;
+
+    self.stack.push(name);
+    let result = match definition {
+      Definition::Expression(expression) => self.evaluate(expression, diagnostics),
+      Definition::Next(None) => Ok(Value::Int(0)),
+      Definition::Next(Some(previous)) => {
+        match self.resolve(previous, diagnostics) {
+          Ok(Value::Int(int)) => Ok(Value::Int(int.wrapping_add(1))),
+          Ok(_) => Err(Error::Invalid(format!("`{previous}` is not an integer"))),
+          Err(error) => Err(error)
+        }
+      }
+    };
+    self.stack.pop();
+
+    self.values.insert(name, result.as_ref().ok().copied());
+
+    match result {
+      Err(Error::Cycle(chain)) if chain[0] == name => {
+        diagnostics.push(Diagnostic::new(
+          span,
+          format!("`{name}` depends on itself: {}", chain.join(" -> "))
+        ));
+        Err(Error::Unresolved)
+      }
+      Err(Error::Invalid(message)) => {
+        diagnostics.push(Diagnostic::new(
+          span,
+          format!("cannot resolve `{name}`: {message}")
+        ));
+        Err(Error::Unresolved)
+      }
+      Err(Error::Unsupported) => Err(Error::Unresolved),
+      result => result
+    }
+  }
+
+  fn evaluate(
+    &mut self,
+    expression: &'a Expression,
+    diagnostics: &mut Vec<Diagnostic>
+  ) -> Result<Value, Error> {
+    let mut eval = |e: &'a Expression| self.evaluate(e, diagnostics);
+
+    match expression {
+      Expression::Literal(Literal::Int { value, .. }) => Ok(Value::Int(*value)),
+      Expression::Literal(Literal::Float(float)) => Ok(Value::Float(*float)),
+      Expression::Literal(Literal::Bool(b)) => Ok(Value::Bool(*b)),
+      Expression::Literal(Literal::Hash(text)) => Ok(Value::Int(joaat(text) as i32 as i64)),
+      Expression::Literal(_) => Err(Error::Unsupported),
+      Expression::Identifier(name) => self.resolve(name, diagnostics),
+      Expression::Call { name, args } => {
+        match (name.as_str(), args.as_slice()) {
+          ("ENUM_TO_INT", [value]) | ("INT_TO_ENUM", [_, value]) => {
+            match eval(value)? {
+              Value::Int(int) => Ok(Value::Int(int)),
+              _ => Err(Error::Invalid(format!("{name} expects an integer")))
+            }
+          }
+          ("TO_FLOAT", [value]) => float(eval(value)?).map(Value::Float),
+          ("FLOOR", [value]) => float(eval(value)?).map(|f| Value::Int(f.floor() as i64)),
+          ("CEIL", [value]) => float(eval(value)?).map(|f| Value::Int(f.ceil() as i64)),
+          ("ROUND", [value]) => float(eval(value)?).map(|f| Value::Int(f.round() as i64)),
+          _ => Err(Error::Unsupported)
+        }
+      }
+      Expression::Add(l, r) => arithmetic(eval(l)?, eval(r)?, i64::wrapping_add, |l, r| l + r),
+      Expression::Subtract(l, r) => arithmetic(eval(l)?, eval(r)?, i64::wrapping_sub, |l, r| l - r),
+      Expression::Multiply(l, r) => arithmetic(eval(l)?, eval(r)?, i64::wrapping_mul, |l, r| l * r),
+      Expression::Divide(l, r) => {
+        match (eval(l)?, eval(r)?) {
+          (Value::Int(_), Value::Int(0)) => Err(Error::Invalid("division by zero".to_owned())),
+          (l, r) => arithmetic(l, r, i64::wrapping_div, |l, r| l / r)
+        }
+      }
+      Expression::Modulo(l, r) => {
+        match integers(eval(l)?, eval(r)?)? {
+          (_, 0) => Err(Error::Invalid("division by zero".to_owned())),
+          (l, r) => Ok(Value::Int(l.wrapping_rem(r)))
+        }
+      }
+      Expression::BitOr(l, r) => integers(eval(l)?, eval(r)?).map(|(l, r)| Value::Int(l | r)),
+      Expression::BitXor(l, r) => integers(eval(l)?, eval(r)?).map(|(l, r)| Value::Int(l ^ r)),
+      Expression::BitAnd(l, r) => integers(eval(l)?, eval(r)?).map(|(l, r)| Value::Int(l & r)),
+      Expression::ShiftLeft(l, r) => {
+        integers(eval(l)?, eval(r)?).map(|(l, r)| Value::Int(l.wrapping_shl(r as u32)))
+      }
+      Expression::ShiftRight(l, r) => {
+        integers(eval(l)?, eval(r)?).map(|(l, r)| Value::Int(l.wrapping_shr(r as u32)))
+      }
+      Expression::Equal(l, r) => comparison(eval(l)?, eval(r)?, |o| o.is_eq()),
+      Expression::NotEqual(l, r) => comparison(eval(l)?, eval(r)?, |o| o.is_ne()),
+      Expression::Less(l, r) => comparison(eval(l)?, eval(r)?, |o| o.is_lt()),
+      Expression::LessEqual(l, r) => comparison(eval(l)?, eval(r)?, |o| o.is_le()),
+      Expression::Greater(l, r) => comparison(eval(l)?, eval(r)?, |o| o.is_gt()),
+      Expression::GreaterEqual(l, r) => comparison(eval(l)?, eval(r)?, |o| o.is_ge()),
+      Expression::And(l, r) => Ok(Value::Bool(boolean(eval(l)?)? && boolean(eval(r)?)?)),
+      Expression::Or(l, r) => Ok(Value::Bool(boolean(eval(l)?)? || boolean(eval(r)?)?)),
+      Expression::Negate(e) => {
+        match eval(e)? {
+          Value::Int(int) => Ok(Value::Int(int.wrapping_neg())),
+          Value::Float(float) => Ok(Value::Float(-float)),
+          Value::Bool(_) => Err(Error::Invalid("cannot negate a BOOL".to_owned()))
+        }
+      }
+      Expression::BitNot(e) => integers(eval(e)?, Value::Int(0)).map(|(e, _)| Value::Int(!e)),
+      Expression::Not(e) => boolean(eval(e)?).map(|b| Value::Bool(!b)),
+      Expression::Index(..) | Expression::Member(..) => Err(Error::Unsupported),
+      Expression::Parentheses(e) => eval(e)
+    }
+  }
+}
+
+fn float(value: Value) -> Result<f32, Error> {
+  value
+    .as_float()
+    .ok_or_else(|| Error::Invalid("expected a number".to_owned()))
+}
+
+fn boolean(value: Value) -> Result<bool, Error> {
+  value
+    .as_bool()
+    .ok_or_else(|| Error::Invalid("expected a BOOL or INT".to_owned()))
+}
+
+fn integers(l: Value, r: Value) -> Result<(i64, i64), Error> {
+  match (l, r) {
+    (Value::Int(l), Value::Int(r)) => Ok((l, r)),
+    _ => Err(Error::Invalid("expected integers".to_owned()))
+  }
+}
+
+/// Integer arithmetic when both sides are integers, float arithmetic when
+/// either side is a float.
+fn arithmetic(
+  l: Value,
+  r: Value,
+  int: impl Fn(i64, i64) -> i64,
+  float: impl Fn(f32, f32) -> f32
+) -> Result<Value, Error> {
+  match (l, r) {
+    (Value::Int(l), Value::Int(r)) => Ok(Value::Int(int(l, r))),
+    (l, r) => {
+      match (l.as_float(), r.as_float()) {
+        (Some(l), Some(r)) => Ok(Value::Float(float(l, r))),
+        _ => Err(Error::Invalid("arithmetic on a BOOL".to_owned()))
+      }
+    }
+  }
+}
+
+fn comparison(
+  l: Value,
+  r: Value,
+  test: impl Fn(std::cmp::Ordering) -> bool
+) -> Result<Value, Error> {
+  let ordering = match (l, r) {
+    (Value::Int(l), Value::Int(r)) => Some(l.cmp(&r)),
+    (Value::Bool(l), Value::Bool(r)) => Some(l.cmp(&r)),
+    (l, r) => {
+      match (l.as_float(), r.as_float()) {
+        (Some(l), Some(r)) => l.partial_cmp(&r),
+        _ => None
+      }
+    }
+  };
+
+  ordering
+    .map(|o| Value::Bool(test(o)))
+    .ok_or_else(|| Error::Invalid("cannot compare these values".to_owned()))
+}
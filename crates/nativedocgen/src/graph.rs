@@ -0,0 +1,182 @@
+use std::{collections::VecDeque, fmt::Write, path::Path};
+
+use indexmap::{IndexMap, IndexSet};
+
+use crate::{
+  includes::IncludeGraph,
+  parser::model::{Declaration, FunctionDefinition}
+};
+
+/// A directed graph that can be written as Graphviz DOT.
+#[derive(Debug, Default)]
+pub struct DotGraph {
+  /// Node names with their DOT shape.
+  nodes: IndexMap<String, &'static str>,
+  edges: IndexSet<(String, String)>
+}
+
+impl DotGraph {
+  /// Builds the graph of files including each other through `USING`.
+  pub fn includes(graph: &IncludeGraph) -> Self {
+    let mut dot = Self::default();
+
+    for (file, includes) in &graph.files {
+      dot.add_node(&file_name(file), "note");
+
+      for include in includes {
+        dot.add_node(&file_name(include), "note");
+        dot.add_edge(&file_name(file), &file_name(include));
+      }
+    }
+
+    dot
+  }
+
+  /// Builds the graph of type references made by struct fields, native and
+  /// typedef signatures and native type aliases. References to built-in types
+  /// are left out.
+  pub fn types(declarations: &[Declaration]) -> Self {
+    let mut dot = Self::default();
+
+    for decl in declarations {
+      match decl {
+        Declaration::Struct(struct_) => dot.add_node(&struct_.name, "box"),
+        Declaration::Enum(enum_) => dot.add_node(&enum_.name, "octagon"),
+        Declaration::NativeType(native_type) => dot.add_node(&native_type.name, "component"),
+        Declaration::Typedef(typedef) => dot.add_node(&typedef.definition.name, "cds"),
+        Declaration::Forward(forward) => dot.add_node(&forward.name, "box"),
+        _ => {}
+      }
+    }
+
+    for decl in declarations {
+      match decl {
+        Declaration::Struct(struct_) => {
+          for field in &struct_.fields {
+            dot.add_type_reference(&struct_.name, &field.type_name);
+          }
+        }
+        Declaration::NativeType(native_type) => {
+          if let Some(alias_for) = &native_type.alias_for {
+            dot.add_type_reference(&native_type.name, alias_for);
+          }
+        }
+        Declaration::Native(native) => dot.add_signature(&native.definition),
+        Declaration::Typedef(typedef) => dot.add_signature(&typedef.definition),
+        _ => {}
+      }
+    }
+
+    dot
+  }
+
+  /// Keeps only the nodes at most `depth` edges away from `focus`, in either
+  /// direction. Returns `None` when `focus` is not part of the graph.
+  pub fn neighbourhood(&self, focus: &str, depth: usize) -> Option<Self> {
+    if !self.nodes.contains_key(focus) {
+      return None;
+    }
+
+    let mut distances = IndexMap::from([(focus, 0)]);
+    let mut queue = VecDeque::from([focus]);
+
+    while let Some(node) = queue.pop_front() {
+      let distance = distances[node];
+      if distance == depth {
+        continue;
+      }
+
+      let neighbours = self.edges.iter().filter_map(|(from, to)| {
+        if from == node {
+          Some(to.as_str())
+        } else if to == node {
+          Some(from.as_str())
+        } else {
+          None
+        }
+      });
+
+      for neighbour in neighbours {
+        if !distances.contains_key(neighbour) {
+          distances.insert(neighbour, distance + 1);
+          queue.push_back(neighbour);
+        }
+      }
+    }
+
+    Some(Self {
+      nodes: self
+        .nodes
+        .iter()
+        .filter(|(name, _)| distances.contains_key(name.as_str()))
+        .map(|(name, shape)| (name.clone(), *shape))
+        .collect(),
+      edges: self
+        .edges
+        .iter()
+        .filter(|(from, to)| {
+          distances.contains_key(from.as_str()) && distances.contains_key(to.as_str())
+        })
+        .cloned()
+        .collect()
+    })
+  }
+
+  pub fn to_dot(&self, name: &str) -> String {
+    let mut dot = String::new();
+
+    writeln!(dot, "digraph {} {{", quote(name)).unwrap();
+
+    for (node, shape) in &self.nodes {
+      writeln!(dot, "  {} [shape={shape}];", quote(node)).unwrap();
+    }
+
+    for (from, to) in &self.edges {
+      writeln!(dot, "  {} -> {};", quote(from), quote(to)).unwrap();
+    }
+
+    dot.push_str("}\n");
+
+    dot
+  }
+
+  fn add_node(&mut self, name: &str, shape: &'static str) {
+    self.nodes.entry(name.to_owned()).or_insert(shape);
+  }
+
+  fn add_edge(&mut self, from: &str, to: &str) {
+    self.edges.insert((from.to_owned(), to.to_owned()));
+  }
+
+  fn add_type_reference(&mut self, from: &str, type_name: &str) {
+    if self.nodes.contains_key(type_name) {
+      self.add_edge(from, type_name);
+    }
+  }
+
+  fn add_signature(&mut self, definition: &FunctionDefinition) {
+    let types = definition
+      .params
+      .iter()
+      .map(|param| &param.type_.base_type)
+      .chain(&definition.return_type);
+
+    for type_name in types {
+      if self.nodes.contains_key(type_name.as_str()) {
+        self.add_node(&definition.name, "ellipse");
+        self.add_edge(&definition.name, type_name);
+      }
+    }
+  }
+}
+
+fn file_name(path: &Path) -> String {
+  path
+    .file_name()
+    .map(|name| name.to_string_lossy().into_owned())
+    .unwrap_or_else(|| path.display().to_string())
+}
+
+fn quote(text: &str) -> String {
+  format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
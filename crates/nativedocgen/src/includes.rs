@@ -0,0 +1,76 @@
+use std::{
+  collections::HashSet,
+  path::{Path, PathBuf}
+};
+
+use indexmap::IndexMap;
+
+/// Finds the file a `USING` directive refers to, looking next to the including
+/// file first and then in each include directory in order.
+pub fn resolve_include(
+  including: &Path,
+  include: &str,
+  include_dirs: &[PathBuf]
+) -> Option<PathBuf> {
+  including
+    .parent()
+    .map(Path::to_path_buf)
+    .into_iter()
+    .chain(include_dirs.iter().cloned())
+    .map(|dir| dir.join(include))
+    .find(|path| path.is_file())
+}
+
+/// Identifies a file regardless of how its path was spelled.
+pub fn file_key(path: &Path) -> PathBuf {
+  path.canonicalize().unwrap_or_else(|_| path.to_owned())
+}
+
+#[derive(Debug, Default)]
+pub struct IncludeGraph {
+  /// Every loaded file with the files it includes, in load order.
+  pub files: IndexMap<PathBuf, Vec<PathBuf>>
+}
+
+impl IncludeGraph {
+  /// Returns every include cycle as the chain of files leading back to its
+  /// first file.
+  pub fn cycles(&self) -> Vec<Vec<&PathBuf>> {
+    let mut cycles = vec![];
+    let mut stack = vec![];
+    let mut done = HashSet::new();
+
+    for file in self.files.keys() {
+      self.visit(file, &mut stack, &mut done, &mut cycles);
+    }
+
+    cycles
+  }
+
+  fn visit<'a>(
+    &'a self,
+    file: &'a PathBuf,
+    stack: &mut Vec<&'a PathBuf>,
+    done: &mut HashSet<&'a PathBuf>,
+    cycles: &mut Vec<Vec<&'a PathBuf>>
+  ) {
+    if done.contains(file) {
+      return;
+    }
+
+    if let Some(index) = stack.iter().position(|f| *f == file) {
+      let mut cycle = stack[index..].to_vec();
+      cycle.push(file);
+      cycles.push(cycle);
+      return;
+    }
+
+    stack.push(file);
+    for include in self.files.get(file).into_iter().flatten() {
+      self.visit(include, stack, done, cycles);
+    }
+    stack.pop();
+
+    done.insert(file);
+  }
+}
@@ -0,0 +1,26 @@
+/// Jenkins one-at-a-time hash of the lowercased input, the hash the game uses
+/// for model names, `HASH("...")` and `HASH_ENUM` members.
+pub fn joaat(text: &str) -> u32 {
+  let mut hash: u32 = 0;
+
+  for byte in text.bytes().map(|b| b.to_ascii_lowercase()) {
+    hash = hash.wrapping_add(byte as u32);
+    hash = hash.wrapping_add(hash << 10);
+    hash ^= hash >> 6;
+  }
+
+  hash = hash.wrapping_add(hash << 3);
+  hash ^= hash >> 11;
+  hash.wrapping_add(hash << 15)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::joaat;
+
+  #[test]
+  fn matches_known_model_hash() {
+    assert_eq!(joaat("adder"), 0xB779A091);
+    assert_eq!(joaat("ADDER"), joaat("adder"));
+  }
+}
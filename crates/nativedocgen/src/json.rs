@@ -1,16 +1,72 @@
 use indexmap::IndexMap;
 use nativedocgen_model::{
-  ConstDefinition, DocumentRoot, EnumValue, Native, NativeParam, StructField, TypeDefinition
+  ConstDefinition, DocumentRoot, EnumKind, EnumValue, GlobalDefinition, HashValue, Native,
+  NativeParam, ResolvedValue, ScriptFunction, SourceLocation, StructField, TypeDefinition
 };
 
-use crate::{crossmap::get_orig_native_hash, parser::model as sch};
+use crate::{
+  crossmap::get_orig_native_hash,
+  diagnostic::Diagnostic,
+  evaluator::{resolve_values, Value},
+  joaat::joaat,
+  parser::model as sch,
+  typecheck::check_types,
+  usages::usage_index
+};
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DocumentOptions {
+  /// Include the file and line each declaration was parsed from.
+  pub sources:            bool,
+  /// Include the script functions calling each native.
+  pub usages:             bool,
+  /// Include script FUNC/PROC helpers in a separate `functions` section.
+  pub functions:          bool,
+  /// Leave out natives declared `DEBUGONLY`.
+  pub exclude_debug_only: bool
+}
+
+impl From<&sch::Span> for SourceLocation {
+  fn from(value: &sch::Span) -> Self {
+    Self {
+      file: value.file.to_string(),
+      line: value.start.line
+    }
+  }
+}
+
+impl From<Value> for ResolvedValue {
+  fn from(value: Value) -> Self {
+    match value {
+      Value::Int(int) => Self::Int(int),
+      Value::Float(float) => Self::Float(float),
+      Value::Bool(b) => Self::Bool(b)
+    }
+  }
+}
+
+/// The joaat hash of `expression` when it is a `HASH("...")` literal.
+fn literal_hash(expression: &sch::Expression) -> Option<HashValue> {
+  match expression {
+    sch::Expression::Literal(sch::Literal::Hash(text)) => {
+      let hash = joaat(text);
+
+      Some(HashValue {
+        signed:   hash as i32,
+        unsigned: hash
+      })
+    }
+    _ => None
+  }
+}
 
 impl From<sch::FunctionParameter> for NativeParam {
   fn from(value: sch::FunctionParameter) -> Self {
     Self {
-      ty:      value.type_.into(),
-      name:    value.name,
-      default: value.default_value.map(|v| v.to_string())
+      ty:           value.type_.into(),
+      name:         value.name,
+      default_hash: value.default_value.as_ref().and_then(literal_hash),
+      default:      value.default_value.map(|v| v.to_string())
     }
   }
 }
@@ -18,22 +74,61 @@ impl From<sch::FunctionParameter> for NativeParam {
 impl From<sch::NativeDeclaration> for Native {
   fn from(value: sch::NativeDeclaration) -> Self {
     Self {
-      name:        value.definition.name,
-      sch_comment: if value.comments.is_empty() {
+      name:           value.definition.name,
+      sch_comment:    if value.comments.is_empty() {
         None
       } else {
         Some(value.comments.join("\r\n"))
       },
-      params:      value
+      params:         value
         .definition
         .params
         .into_iter()
         .map(NativeParam::from)
         .collect(),
-      return_type: value
+      return_type:    value
+        .definition
+        .return_type
+        .unwrap_or_else(|| "void".to_owned()),
+      configurations: vec![],
+      source:         Some((&value.span).into()),
+      usages:         vec![],
+      is_debug_only:  value.definition.is_debug_only
+    }
+  }
+}
+
+impl From<sch::FunctionDeclaration> for ScriptFunction {
+  fn from(value: sch::FunctionDeclaration) -> Self {
+    Self {
+      name:          value.definition.name,
+      comment:       if value.comments.is_empty() {
+        None
+      } else {
+        Some(value.comments.join("\r\n"))
+      },
+      params:        value
+        .definition
+        .params
+        .into_iter()
+        .map(NativeParam::from)
+        .collect(),
+      return_type:   value
         .definition
         .return_type
-        .unwrap_or_else(|| "void".to_owned())
+        .unwrap_or_else(|| "void".to_owned()),
+      source:        (&value.span).into(),
+      is_debug_only: value.definition.is_debug_only
+    }
+  }
+}
+
+impl From<sch::EnumKind> for EnumKind {
+  fn from(value: sch::EnumKind) -> Self {
+    match value {
+      sch::EnumKind::Enum => Self::Enum,
+      sch::EnumKind::Strict => Self::StrictEnum,
+      sch::EnumKind::Hash => Self::HashEnum
     }
   }
 }
@@ -41,8 +136,10 @@ impl From<sch::NativeDeclaration> for Native {
 impl From<sch::EnumField> for EnumValue {
   fn from(value: sch::EnumField) -> Self {
     Self {
-      comment: value.comment,
-      value:   value.value.map(|v| v.to_string())
+      comment:        value.comment,
+      hash:           value.value.as_ref().and_then(literal_hash),
+      value:          value.value.map(|v| v.to_string()),
+      resolved_value: None
     }
   }
 }
@@ -66,11 +163,13 @@ impl From<sch::EnumDeclaration> for TypeDefinition {
       } else {
         Some(value.comments.join("\r\n"))
       },
+      kind:    value.kind.into(),
       values:  value
         .values
         .into_iter()
         .map(|v| (v.name.clone(), EnumValue::from(v)))
-        .collect::<IndexMap<_, _>>()
+        .collect::<IndexMap<_, _>>(),
+      source:  Some((&value.span).into())
     }
   }
 }
@@ -87,7 +186,8 @@ impl From<sch::StructDeclaration> for TypeDefinition {
         .fields
         .into_iter()
         .map(|f| (f.name.clone(), StructField::from(f)))
-        .collect::<IndexMap<_, _>>()
+        .collect::<IndexMap<_, _>>(),
+      source:  Some((&value.span).into())
     }
   }
 }
@@ -96,7 +196,31 @@ impl From<sch::NativeTypeDeclaration> for TypeDefinition {
   fn from(value: sch::NativeTypeDeclaration) -> Self {
     Self::NativeType {
       comment:   value.comment,
-      alias_for: value.alias_for
+      alias_for: value.alias_for,
+      source:    Some((&value.span).into())
+    }
+  }
+}
+
+impl From<sch::TypedefDeclaration> for TypeDefinition {
+  fn from(value: sch::TypedefDeclaration) -> Self {
+    Self::FunctionPointer {
+      comment:     if value.comments.is_empty() {
+        None
+      } else {
+        Some(value.comments.join("\r\n"))
+      },
+      return_type: value
+        .definition
+        .return_type
+        .unwrap_or_else(|| "void".to_owned()),
+      params:      value
+        .definition
+        .params
+        .into_iter()
+        .map(NativeParam::from)
+        .collect(),
+      source:      Some((&value.span).into())
     }
   }
 }
@@ -104,48 +228,225 @@ impl From<sch::NativeTypeDeclaration> for TypeDefinition {
 impl From<sch::ConstDeclaration> for ConstDefinition {
   fn from(value: sch::ConstDeclaration) -> Self {
     Self {
+      comment:        value.comment,
+      type_name:      value.type_name,
+      hash:           literal_hash(&value.value),
+      value:          value.value.to_string(),
+      resolved_value: None,
+      source:         Some((&value.span).into())
+    }
+  }
+}
+
+impl From<sch::TweakDeclaration> for GlobalDefinition {
+  fn from(value: sch::TweakDeclaration) -> Self {
+    Self::Tweak {
       comment:   value.comment,
       type_name: value.type_name,
-      value:     value.value.to_string()
+      value:     value.value.to_string(),
+      source:    Some((&value.span).into())
+    }
+  }
+}
+
+impl From<sch::GlobalDeclaration> for GlobalDefinition {
+  fn from(value: sch::GlobalDeclaration) -> Self {
+    Self::Variable {
+      comment:       value.comment,
+      type_name:     value.type_name,
+      array_size:    value.array_size.map(|s| s.to_string()),
+      default_value: value.default_value.map(|v| v.to_string()),
+      source:        Some((&value.span).into())
     }
   }
 }
 
-pub fn to_document_root(mut value: Vec<sch::Declaration>) -> DocumentRoot {
+pub fn to_document_root(
+  mut value: Vec<sch::Declaration>,
+  options: &DocumentOptions,
+  diagnostics: &mut Vec<Diagnostic>
+) -> DocumentRoot {
   let mut types: IndexMap<String, TypeDefinition> = Default::default();
   let mut constants: IndexMap<String, ConstDefinition> = Default::default();
+  let mut globals: IndexMap<String, GlobalDefinition> = Default::default();
   let mut natives: IndexMap<String, Native> = Default::default();
+  let mut functions: IndexMap<String, ScriptFunction> = Default::default();
+  let mut forwards: Vec<sch::ForwardDeclaration> = vec![];
+  let mut usages = if options.usages {
+    usage_index(&value)
+  } else {
+    Default::default()
+  };
+  let resolved = resolve_values(&value, diagnostics);
+  check_types(&value, diagnostics);
 
   // rust-analyzer bug, it gets confused by `sch::Declaration::Const`
   #[allow(unreachable_code)]
   for decl in value.drain(0..) {
     match decl {
       sch::Declaration::Enum(enum_decl) => {
-        types.insert(enum_decl.name.clone(), enum_decl.into());
+        let name = enum_decl.name.clone();
+        let mut definition = TypeDefinition::from(enum_decl);
+
+        if let TypeDefinition::Enum { values, .. } = &mut definition {
+          for (name, value) in values.iter_mut() {
+            value.resolved_value = resolved.get(name).copied().map(ResolvedValue::from);
+
+            if let (None, Some(ResolvedValue::Int(int))) = (&value.value, value.resolved_value) {
+              value.value = Some(int.to_string());
+            }
+          }
+        }
+
+        types.insert(name, definition);
       }
       sch::Declaration::Struct(struct_decl) => {
         types.insert(struct_decl.name.clone(), struct_decl.into());
       }
       sch::Declaration::Comment(_) => {}
       sch::Declaration::Using(_) => {}
-      sch::Declaration::Function(_) => {}
+      sch::Declaration::Function(function) => {
+        if options.functions {
+          functions.insert(function.definition.name.clone(), function.into());
+        }
+      }
+      sch::Declaration::Script(_) => {}
+      sch::Declaration::Native(native)
+        if options.exclude_debug_only && native.definition.is_debug_only => {}
       sch::Declaration::Native(native) => {
         if let Some(hash) = get_orig_native_hash(native.native_hash) {
-          natives.insert(format!("0x{:016X}", hash), native.into());
+          let mut native = Native::from(native);
+          native.usages = usages.remove(&native.name).unwrap_or_default();
+
+          natives.insert(format!("0x{:016X}", hash), native);
         }
       }
       sch::Declaration::NativeType(type_decl) => {
         types.insert(type_decl.name.clone(), type_decl.into());
       }
+      sch::Declaration::Typedef(typedef) => {
+        types.insert(typedef.definition.name.clone(), typedef.into());
+      }
+      sch::Declaration::Forward(forward) => forwards.push(forward),
       sch::Declaration::Const(const_decl) => {
-        constants.insert(const_decl.name.clone(), const_decl.into());
+        let name = const_decl.name.clone();
+        let mut definition = ConstDefinition::from(const_decl);
+        definition.resolved_value = resolved.get(&name).copied().map(ResolvedValue::from);
+
+        constants.insert(name, definition);
+      }
+      sch::Declaration::Tweak(tweak) => {
+        globals.insert(tweak.name.clone(), tweak.into());
+      }
+      sch::Declaration::Global(global) => {
+        globals.insert(global.name.clone(), global.into());
       }
     }
   }
 
-  DocumentRoot {
+  link_forward_declarations(&forwards, &types, diagnostics);
+
+  let mut root = DocumentRoot {
     types,
     constants,
-    natives
+    globals,
+    natives,
+    functions
+  };
+
+  if !options.sources {
+    strip_sources(&mut root);
   }
+
+  root
+}
+
+/// Checks every `FORWARD ENUM`/`FORWARD STRUCT` against the definitions
+/// collected from all files.
+fn link_forward_declarations(
+  forwards: &[sch::ForwardDeclaration],
+  types: &IndexMap<String, TypeDefinition>,
+  diagnostics: &mut Vec<Diagnostic>
+) {
+  for forward in forwards {
+    let problem = match (forward.kind, types.get(&forward.name)) {
+      (_, None) => "has no matching definition",
+      (sch::ForwardKind::Enum, Some(TypeDefinition::Enum { .. }))
+      | (sch::ForwardKind::Struct, Some(TypeDefinition::Struct { .. })) => continue,
+      _ => "does not match the kind of its definition"
+    };
+
+    diagnostics.push(Diagnostic::new(
+      &forward.span,
+      format!("FORWARD {} {} {problem}", forward.kind, forward.name)
+    ));
+  }
+}
+
+fn strip_sources(root: &mut DocumentRoot) {
+  for ty in root.types.values_mut() {
+    match ty {
+      TypeDefinition::Enum { source, .. }
+      | TypeDefinition::Struct { source, .. }
+      | TypeDefinition::NativeType { source, .. }
+      | TypeDefinition::FunctionPointer { source, .. } => *source = None
+    }
+  }
+
+  for constant in root.constants.values_mut() {
+    constant.source = None;
+  }
+
+  for global in root.globals.values_mut() {
+    match global {
+      GlobalDefinition::Variable { source, .. } | GlobalDefinition::Tweak { source, .. } => {
+        *source = None
+      }
+    }
+  }
+
+  for native in root.natives.values_mut() {
+    native.source = None;
+  }
+}
+
+/// Combines the documents generated for each configuration, tagging every
+/// native with the configurations it was found in.
+pub fn merge_configurations(roots: Vec<(String, DocumentRoot)>) -> DocumentRoot {
+  let mut merged = DocumentRoot {
+    types:     Default::default(),
+    constants: Default::default(),
+    globals:   Default::default(),
+    natives:   Default::default(),
+    functions: Default::default()
+  };
+
+  for (configuration, root) in roots {
+    for (name, ty) in root.types {
+      merged.types.entry(name).or_insert(ty);
+    }
+
+    for (name, constant) in root.constants {
+      merged.constants.entry(name).or_insert(constant);
+    }
+
+    for (name, global) in root.globals {
+      merged.globals.entry(name).or_insert(global);
+    }
+
+    for (hash, native) in root.natives {
+      merged
+        .natives
+        .entry(hash)
+        .or_insert(native)
+        .configurations
+        .push(configuration.clone());
+    }
+
+    for (name, function) in root.functions {
+      merged.functions.entry(name).or_insert(function);
+    }
+  }
+
+  merged
 }
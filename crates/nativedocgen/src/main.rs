@@ -1,63 +1,249 @@
 use std::{
+  collections::{HashSet, VecDeque},
   fs::{create_dir_all, read_to_string, File},
   io::Write,
-  path::PathBuf
+  path::{Path, PathBuf}
 };
 
 use clap::{Parser, ValueHint};
 use nativedocgen_model::DocumentRoot;
-use peg::{error::ParseError, str::LineCol};
 
-use crate::{json::to_document_root, parser::sch_parser};
-
-use self::parser::model::Declaration;
+use crate::{
+  diagnostic::Diagnostic,
+  graph::DotGraph,
+  includes::{file_key, resolve_include, IncludeGraph},
+  json::{merge_configurations, to_document_root, DocumentOptions},
+  parser::{model::Declaration, parse_sch, ParseOutput, SkippedDeclaration, SourceFile},
+  preprocessor::{preprocess, Defines}
+};
 
 mod crossmap;
+mod diagnostic;
+mod evaluator;
+mod graph;
+mod includes;
+mod joaat;
 mod json;
 mod parser;
+mod preprocessor;
+mod typecheck;
+mod usages;
 
 #[derive(clap::Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-  /// Glob pattern for sch files
+  /// Glob pattern for sch header and sc script files
   #[arg(short, long, value_hint = ValueHint::FilePath)]
   sch_files: String,
 
   /// Output directory
   #[arg(short, long, value_hint = ValueHint::DirPath)]
-  output: String
-}
-
-type ProcessResult = (String, Result<Vec<Declaration>, ParseError<LineCol>>);
-
-fn process_file_contents(path: &PathBuf, contents: String) -> ProcessResult {
-  (
-    path
-      .file_name()
-      .and_then(|n| n.to_str())
-      .map(|n| n.to_owned())
-      .unwrap(),
-    sch_parser::sch(&contents)
-  )
-}
-
-fn process_files(pattern: String) -> anyhow::Result<Vec<ProcessResult>> {
-  let result = glob::glob(&pattern)?
-    .filter_map(|entry| {
-      match &entry {
-        Ok(path) if path.is_file() => {
-          if let Ok(contents) = read_to_string(path) {
-            Some(process_file_contents(&path, contents))
-          } else {
-            None
-          }
+  output: String,
+
+  /// Preprocessor symbol used by #IF/#IFDEF/#IFNDEF, value defaults to 1
+  #[arg(short = 'D', long = "define", value_name = "SYMBOL[=VALUE]", value_parser = parse_define)]
+  defines: Vec<(String, String)>,
+
+  /// Directory searched for files named in USING directives, can be repeated
+  #[arg(short = 'I', long = "include-dir", value_hint = ValueHint::DirPath)]
+  include_dirs: Vec<PathBuf>,
+
+  /// Named symbol set to generate for, e.g. debug:IS_DEBUG_BUILD=1, can be repeated
+  #[arg(
+    short,
+    long = "configuration",
+    value_name = "NAME[:SYMBOL[=VALUE],...]",
+    value_parser = parse_configuration
+  )]
+  configurations: Vec<Configuration>,
+
+  /// Include the source file and line of every native, type and constant
+  #[arg(long)]
+  sources: bool,
+
+  /// Include the script FUNC/PROCs calling each native
+  #[arg(long)]
+  usages: bool,
+
+  /// Export script FUNC/PROC helpers in a separate functions section
+  #[arg(long)]
+  functions: bool,
+
+  /// Leave DEBUGONLY natives out of the output
+  #[arg(long)]
+  exclude_debug_only: bool,
+
+  #[command(subcommand)]
+  command: Option<Command>
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+  /// Write a dependency graph as Graphviz DOT instead of natives.json
+  Graph(GraphArgs)
+}
+
+#[derive(clap::Args, Debug)]
+struct GraphArgs {
+  /// Graph to write
+  #[arg(value_enum)]
+  kind: GraphKind,
+
+  /// Only keep this file or type and the nodes around it
+  #[arg(long, value_name = "NAME")]
+  focus: Option<String>,
+
+  /// How many edges away from the focused node to keep
+  #[arg(long, default_value_t = 1, requires = "focus")]
+  depth: usize
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum GraphKind {
+  /// Files including each other through USING
+  Includes,
+  /// Types referenced by struct fields, native signatures and native type aliases
+  Types
+}
+
+impl GraphKind {
+  fn name(self) -> &'static str {
+    match self {
+      GraphKind::Includes => "includes",
+      GraphKind::Types => "types"
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+struct Configuration {
+  name:    String,
+  defines: Vec<(String, String)>
+}
+
+fn parse_define(define: &str) -> Result<(String, String), String> {
+  let (symbol, value) = define.split_once('=').unwrap_or((define, "1"));
+
+  if symbol.is_empty() {
+    return Err("symbol name is empty".to_owned());
+  }
+
+  Ok((symbol.to_owned(), value.to_owned()))
+}
+
+fn parse_configuration(configuration: &str) -> Result<Configuration, String> {
+  let (name, defines) = configuration.split_once(':').unwrap_or((configuration, ""));
+
+  if name.is_empty() {
+    return Err("configuration name is empty".to_owned());
+  }
+
+  Ok(Configuration {
+    name:    name.to_owned(),
+    defines: defines
+      .split(',')
+      .filter(|d| !d.is_empty())
+      .map(parse_define)
+      .collect::<Result<_, _>>()?
+  })
+}
+
+struct ProcessResult {
+  path:   PathBuf,
+  output: anyhow::Result<ParseOutput>
+}
+
+fn process_file_contents(path: &Path, contents: String, defines: &Defines) -> ProcessResult {
+  let name = path
+    .file_name()
+    .and_then(|n| n.to_str())
+    .map(|n| n.to_owned())
+    .unwrap();
+
+  let output = preprocess(&contents, defines)
+    .map(|contents| parse_sch(&contents, &SourceFile::new(&name, &contents)))
+    .map_err(anyhow::Error::from);
+
+  ProcessResult {
+    path: path.to_owned(),
+    output
+  }
+}
+
+/// Parses every file matched by `pattern` along with the files they include
+/// through `USING`, reporting missing includes and include cycles.
+fn process_files(
+  pattern: &str,
+  defines: &Defines,
+  include_dirs: &[PathBuf],
+  diagnostics: &mut Vec<Diagnostic>
+) -> anyhow::Result<(Vec<ProcessResult>, IncludeGraph)> {
+  let mut queue = glob::glob(pattern)?
+    .filter_map(|entry| entry.ok())
+    .filter(|path| path.is_file())
+    .collect::<VecDeque<_>>();
+
+  let mut graph = IncludeGraph::default();
+  let mut results = vec![];
+
+  while let Some(path) = queue.pop_front() {
+    let key = file_key(&path);
+    if graph.files.contains_key(&key) {
+      continue;
+    }
+
+    let Ok(contents) = read_to_string(&path) else {
+      graph.files.insert(key, vec![]);
+      continue;
+    };
+
+    let result = process_file_contents(&path, contents, defines);
+    let mut includes = vec![];
+
+    let usings = result
+      .output
+      .iter()
+      .flat_map(|output| &output.declarations)
+      .filter_map(|decl| {
+        match decl {
+          Declaration::Using(using) => Some(using),
+          _ => None
+        }
+      });
+
+    for using in usings {
+      match resolve_include(&path, &using.path, include_dirs) {
+        Some(include) => {
+          includes.push(file_key(&include));
+          queue.push_back(include);
+        }
+        None => {
+          diagnostics.push(Diagnostic::new(
+            &using.span,
+            format!("cannot find included file `{}`", using.path)
+          ))
         }
-        _ => None
       }
-    })
-    .collect::<Vec<_>>();
+    }
+
+    graph.files.insert(key, includes);
+    results.push(result);
+  }
+
+  for cycle in graph.cycles() {
+    let chain = cycle
+      .iter()
+      .map(|file| file.display().to_string())
+      .collect::<Vec<_>>()
+      .join(" -> ");
+
+    diagnostics.push(Diagnostic {
+      span:    None,
+      message: format!("include cycle: {chain}")
+    });
+  }
 
-  Ok(result)
+  Ok((results, graph))
 }
 
 fn save_natives_json(output_dir: &str, document: DocumentRoot) -> anyhow::Result<()> {
@@ -70,18 +256,49 @@ fn save_natives_json(output_dir: &str, document: DocumentRoot) -> anyhow::Result
   Ok(())
 }
 
-fn main() -> anyhow::Result<()> {
-  let args = Args::parse();
+fn report_skipped_declarations(path: &Path, skipped: &[SkippedDeclaration]) {
+  if skipped.is_empty() {
+    return;
+  }
 
-  create_dir_all(&args.output)?;
+  println!(
+    "Skipped {} declaration(s) in {}:",
+    skipped.len(),
+    path.display()
+  );
+
+  for SkippedDeclaration { span, error } in skipped {
+    let lines = if span.start.line == span.end.line {
+      format!("line {}", span.start.line)
+    } else {
+      format!("lines {}-{}", span.start.line, span.end.line)
+    };
+
+    match error {
+      Some(error) => println!("  {lines}: {error}"),
+      None => println!("  {lines}: unexpected trailing input")
+    }
+  }
+}
 
-  let decls = process_files(args.sch_files)?
+fn parse_files(
+  pattern: &str,
+  defines: &Defines,
+  include_dirs: &[PathBuf],
+  diagnostics: &mut Vec<Diagnostic>
+) -> anyhow::Result<(Vec<Declaration>, IncludeGraph)> {
+  let (results, graph) = process_files(pattern, defines, include_dirs, diagnostics)?;
+
+  let decls = results
     .into_iter()
     .filter_map(|result| {
-      match result {
-        (_, Ok(decls)) => Some(decls),
-        (name, Err(e)) => {
-          println!("Failed to parse {name}:\r\n{e}");
+      match result.output {
+        Ok(output) => {
+          report_skipped_declarations(&result.path, &output.skipped);
+          Some(output.declarations)
+        }
+        Err(e) => {
+          println!("Failed to parse {}:\r\n{e}", result.path.display());
           None
         }
       }
@@ -89,9 +306,126 @@ fn main() -> anyhow::Result<()> {
     .flatten()
     .collect::<Vec<_>>();
 
-  println!("Generating natives.json");
+  Ok((decls, graph))
+}
+
+fn generate(
+  pattern: &str,
+  defines: &Defines,
+  include_dirs: &[PathBuf],
+  options: &DocumentOptions,
+  diagnostics: &mut Vec<Diagnostic>
+) -> anyhow::Result<DocumentRoot> {
+  let (decls, _) = parse_files(pattern, defines, include_dirs, diagnostics)?;
+
+  Ok(to_document_root(decls, options, diagnostics))
+}
+
+/// Prints every diagnostic once, even when several configurations found it.
+fn report_diagnostics(diagnostics: Vec<Diagnostic>) {
+  let mut reported = HashSet::new();
+
+  for diagnostic in diagnostics {
+    let message = diagnostic.to_string();
+
+    if reported.insert(message.clone()) {
+      println!("{message}");
+    }
+  }
+}
+
+fn generate_graph(
+  pattern: &str,
+  defines: &Defines,
+  include_dirs: &[PathBuf],
+  args: &GraphArgs
+) -> anyhow::Result<String> {
+  let mut diagnostics = vec![];
+
+  let (decls, includes) = parse_files(pattern, defines, include_dirs, &mut diagnostics)?;
+  report_diagnostics(diagnostics);
+
+  let graph = match args.kind {
+    GraphKind::Includes => DotGraph::includes(&includes),
+    GraphKind::Types => DotGraph::types(&decls)
+  };
+
+  let graph = match &args.focus {
+    Some(focus) => {
+      graph
+        .neighbourhood(focus, args.depth)
+        .ok_or_else(|| anyhow::anyhow!("`{focus}` is not part of the {} graph", args.kind.name()))?
+    }
+    None => graph
+  };
+
+  Ok(graph.to_dot(args.kind.name()))
+}
+
+fn main() -> anyhow::Result<()> {
+  let args = Args::parse();
+
+  create_dir_all(&args.output)?;
+
+  let defines = args.defines.into_iter().collect::<Defines>();
+
+  if let Some(Command::Graph(graph_args)) = &args.command {
+    let file_name = format!("{}.dot", graph_args.kind.name());
+    println!("Generating {file_name}");
+
+    let dot = generate_graph(&args.sch_files, &defines, &args.include_dirs, graph_args)?;
+    File::create(format!("{}/{file_name}", args.output))?.write_all(dot.as_bytes())?;
+
+    return Ok(());
+  }
+
+  let options = DocumentOptions {
+    sources:            args.sources,
+    usages:             args.usages,
+    functions:          args.functions,
+    exclude_debug_only: args.exclude_debug_only
+  };
+
+  let mut diagnostics = vec![];
+
+  let root = if args.configurations.is_empty() {
+    println!("Generating natives.json");
+
+    generate(
+      &args.sch_files,
+      &defines,
+      &args.include_dirs,
+      &options,
+      &mut diagnostics
+    )?
+  } else {
+    let roots = args
+      .configurations
+      .into_iter()
+      .map(|configuration| {
+        println!("Generating natives.json for {}", configuration.name);
+
+        let mut defines = defines.clone();
+        defines.extend(configuration.defines);
+
+        Ok((
+          configuration.name,
+          generate(
+            &args.sch_files,
+            &defines,
+            &args.include_dirs,
+            &options,
+            &mut diagnostics
+          )?
+        ))
+      })
+      .collect::<anyhow::Result<Vec<_>>>()?;
+
+    merge_configurations(roots)
+  };
+
+  report_diagnostics(diagnostics);
 
-  let root = to_document_root(decls);
   save_natives_json(&args.output, root)?;
 
   Ok(())
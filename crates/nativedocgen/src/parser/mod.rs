@@ -1,26 +1,108 @@
 pub mod model;
 
+use std::rc::Rc;
+
 use model::{
-  ConstDeclaration, Declaration, EnumDeclaration, EnumField, Expression, FunctionDeclaration,
-  FunctionDefinition, FunctionParameter, FunctionParameterType, Literal, NativeDeclaration,
-  NativeTypeDeclaration, StructDeclaration, StructField
+  AssignmentOperator, CommentDeclaration, ConditionalBranch, ConstDeclaration, Declaration,
+  EnumDeclaration, EnumField, EnumKind, Expression, ForwardDeclaration, ForwardKind,
+  FunctionDeclaration, FunctionDefinition, FunctionParameter, FunctionParameterType,
+  GlobalDeclaration, Literal, NativeDeclaration, NativeTypeDeclaration, ScriptDeclaration, Span,
+  Statement, StatementKind, StructDeclaration, StructField, SwitchCase, TweakDeclaration,
+  TypedefDeclaration, UsingDeclaration
 };
+use peg::{error::ParseError, str::LineCol, ParseLiteral};
+
+/// Maps byte offsets of a parsed file to line/column positions.
+pub struct SourceFile<'a> {
+  name:        Rc<str>,
+  contents:    &'a str,
+  line_starts: Vec<usize>
+}
+
+impl<'a> SourceFile<'a> {
+  pub fn new(name: &str, contents: &'a str) -> Self {
+    Self {
+      name: name.into(),
+      contents,
+      line_starts: std::iter::once(0)
+        .chain(contents.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+    }
+  }
+
+  pub fn position(&self, offset: usize) -> LineCol {
+    let line = self.line_starts.partition_point(|&start| start <= offset);
+    let line_start = self.line_starts[line - 1];
+
+    LineCol {
+      line,
+      column: self.contents[line_start..offset].chars().count() + 1,
+      offset
+    }
+  }
+
+  pub fn span(&self, start: usize, end: usize) -> Span {
+    Span {
+      file:  self.name.clone(),
+      start: self.position(start),
+      end:   self.position(end)
+    }
+  }
+}
 
 peg::parser! {
-  pub grammar sch_parser() for str {
+  pub grammar sch_parser(source: &SourceFile<'input>) for str {
     pub rule sch() -> Vec<Declaration>
       = eol()? declarations:(declaration() ** eol()) eol()? eof() {
         declarations
       }
 
+    pub rule sch_recovering() -> Vec<Result<Declaration, Span>>
+      = eol()? items:(recoverable_declaration() ** eol()) eol()? eof() {
+        items
+      }
+
+    rule recoverable_declaration() -> Result<Declaration, Span>
+      = declaration:declaration() &(eol() / eof()) {
+        Ok(declaration)
+      }
+      / start:position!() (!resync_point() [_])+ end:position!() {
+        Err(source.span(start, end))
+      }
+
+    /// The end of a line followed by the start of a top-level declaration,
+    /// including the comments directly above it.
+    rule resync_point()
+      = one_eol() (indent() comment() one_eol())* indent() resync_keyword()
+
+    rule resync_keyword()
+      = "CONST_"
+      / "TWEAK_"
+      / declaration_keyword()
+
+    rule declaration_keyword()
+      = ("NATIVE" / "STRUCT" / "ENUM" / "STRICT_ENUM" / "HASH_ENUM" / "FUNC" / "PROC" / "DEBUGONLY" / "USING" / "TYPEDEF" / "FORWARD" / "SCRIPT") !identifier_char()
+
+    /// Words that can never be the type of a global variable.
+    rule reserved_word()
+      = "CONST_"
+      / "TWEAK_"
+      / declaration_keyword()
+      / ("ENDSTRUCT" / "ENDENUM" / "ENDFUNC" / "ENDPROC" / "ENDSCRIPT") !identifier_char()
+
     rule declaration() -> Declaration
       = using_declaration()
       / native_declaration()
       / native_type_declaration()
+      / typedef_declaration()
+      / forward_declaration()
       / function_declaration()
+      / script_declaration()
       / struct_declaration()
       / enum_declaration()
       / const_declaration()
+      / tweak_declaration()
+      / global_declaration()
       / comment_declaration()
 
     rule native_type_declaration() -> Declaration
@@ -29,8 +111,8 @@ peg::parser! {
       }
 
     rule native_type() -> NativeTypeDeclaration
-      = "NATIVE" _ ident:identifier() _ alias:type_alias()? _ comment:comment()? {
-        NativeTypeDeclaration { name: ident, alias_for: alias, comment }
+      = start:position!() "NATIVE" inline_space() ident:identifier() inline_space() alias:type_alias()? inline_space() comment:comment()? end:position!() {
+        NativeTypeDeclaration { name: ident, alias_for: alias, comment, span: source.span(start, end) }
       }
 
     rule type_alias() -> String
@@ -38,35 +120,262 @@ peg::parser! {
         ident
       }
 
-
     rule function_declaration() -> Declaration
       = func:function() {
         Declaration::Function(func)
       }
 
     rule function() -> FunctionDeclaration
-      = comments:comments() one_eol()? definition:function_definition() body:$((!("ENDFUNC" / "ENDPROC") v:[_] {v})+) ("ENDFUNC" / "ENDPROC") {
-        FunctionDeclaration { comments, definition, body: body.to_owned() }
+      = comments:comments() one_eol()? start:position!() definition:function_definition() body:body() ("ENDFUNC" / "ENDPROC") end:position!() {
+        FunctionDeclaration { comments, definition, body, span: source.span(start, end) }
+      }
+
+    rule script_declaration() -> Declaration
+      = script:script() {
+        Declaration::Script(script)
+      }
+
+    rule script() -> ScriptDeclaration
+      = start:position!() "SCRIPT" !identifier_char() _ name:identifier()? _ params:function_params()? body:body() "ENDSCRIPT" end:position!() {
+        ScriptDeclaration { name, params: params.unwrap_or_default(), body, span: source.span(start, end) }
+      }
+
+    /// Statements that don't parse are recovered locally, see `statement()`.
+    /// Only a body that still doesn't fit, e.g. one with a stray `ENDIF`, is
+    /// kept line by line, parsing single-line statements and keeping
+    /// everything else as raw text.
+    rule body() -> Vec<Statement>
+      = body:block() &body_end() {
+        body
+      }
+      / raw_body()
+
+    rule raw_body() -> Vec<Statement>
+      = statement_separator() lines:(raw_line() ** statement_separator()) statement_separator()? {
+        lines
+      }
+
+    rule raw_line() -> Statement
+      = start:position!() !block_end() kind:simple_statement_kind() end:position!() &statement_end() {
+        Statement { kind, span: source.span(start, end) }
+      }
+      / start:position!() !body_end() text:$([^'\r' | '\n']+) end:position!() {
+        Statement { kind: StatementKind::Unknown(text.trim_end().to_owned()), span: source.span(start, end) }
+      }
+
+    rule body_end()
+      = ("ENDFUNC" / "ENDPROC" / "ENDSCRIPT") word_end()
+
+    rule block() -> Vec<Statement>
+      = statement_separator() statements:(statement() ** statement_separator()) statement_separator()? {
+        statements
+      }
+
+    rule statement_separator()
+      = quiet!{(_ line_comment()? one_eol())+ _}
+      / expected!("end of line")
+
+    rule statement_end()
+      = _ line_comment()? (one_eol() / eof())
+
+    /// Statements that fail to parse are kept as raw text so a single
+    /// unsupported construct doesn't reject the whole function.
+    rule statement() -> Statement
+      = start:position!() kind:statement_kind() end:position!() &statement_end() {
+        Statement { kind, span: source.span(start, end) }
+      }
+      / start:position!() kind:unknown_block() end:position!() &statement_end() {
+        Statement { kind, span: source.span(start, end) }
+      }
+      / start:position!() !block_end() text:$([^'\r' | '\n']+) end:position!() {
+        Statement { kind: StatementKind::Unknown(text.trim_end().to_owned()), span: source.span(start, end) }
+      }
+
+    /// A block statement whose header doesn't parse, e.g. an `IF` with its
+    /// condition spread over several lines. The header is kept as text and
+    /// the body is still parsed up to the matching end keyword.
+    rule unknown_block() -> StatementKind
+      = unknown_block_of("IF", "ENDIF")
+      / unknown_block_of("WHILE", "ENDWHILE")
+      / unknown_block_of("REPEAT", "ENDREPEAT")
+      / unknown_block_of("FOR", "ENDFOR")
+      / unknown_block_of("SWITCH", "ENDSWITCH")
+
+    rule unknown_block_of(opener: &'static str, end: &'static str) -> StatementKind
+      = header:$(##parse_string_literal(opener) word_end() [^'\r' | '\n']*) body:block() sections:unknown_block_section()* ##parse_string_literal(end) word_end() {
+        StatementKind::UnknownBlock {
+          header: header.trim_end().to_owned(),
+          body: body.into_iter().chain(sections.into_iter().flatten()).collect()
+        }
+      }
+
+    /// An `ELIF`, `ELSE`, `CASE` or `DEFAULT` line inside an unknown block,
+    /// kept as text and followed by its body.
+    rule unknown_block_section() -> Vec<Statement>
+      = start:position!() text:$(("ELIF" / "ELSE" / "CASE" / "DEFAULT") word_end() [^'\r' | '\n']*) end:position!() body:block() {
+        let label = Statement { kind: StatementKind::Unknown(text.trim_end().to_owned()), span: source.span(start, end) };
+
+        std::iter::once(label).chain(body).collect()
+      }
+
+    rule statement_kind() -> StatementKind
+      = if_statement()
+      / while_statement()
+      / repeat_statement()
+      / for_statement()
+      / switch_statement()
+      / simple_statement_kind()
+
+    /// Statements that fit on a single line.
+    rule simple_statement_kind() -> StatementKind
+      = return_statement()
+      / "BREAKLOOP" word_end() { StatementKind::BreakLoop }
+      / "BREAK" word_end() { StatementKind::Break }
+      / "RELOOP" word_end() { StatementKind::Reloop }
+      / "EXIT" word_end() { StatementKind::Exit }
+      / variable_declaration()
+      / increment_statement()
+      / assignment()
+      / call_statement()
+
+    rule if_statement() -> StatementKind
+      = start:position!() "IF" word_end() _ condition:expression() end:position!() body:block() elifs:elif_branch()* else_body:else_branch()? "ENDIF" word_end() {
+        let mut branches = vec![ConditionalBranch { condition, body, span: source.span(start, end) }];
+        branches.extend(elifs);
+
+        StatementKind::If { branches, else_body }
+      }
+
+    rule elif_branch() -> ConditionalBranch
+      = start:position!() "ELIF" word_end() _ condition:expression() end:position!() body:block() {
+        ConditionalBranch { condition, body, span: source.span(start, end) }
+      }
+
+    rule else_branch() -> Vec<Statement>
+      = "ELSE" word_end() body:block() {
+        body
+      }
+
+    rule while_statement() -> StatementKind
+      = "WHILE" word_end() _ condition:expression() body:block() "ENDWHILE" word_end() {
+        StatementKind::While { condition, body }
+      }
+
+    rule repeat_statement() -> StatementKind
+      = "REPEAT" word_end() _ count:expression() _ counter:lvalue() body:block() "ENDREPEAT" word_end() {
+        StatementKind::Repeat { count, counter, body }
+      }
+
+    rule for_statement() -> StatementKind
+      = "FOR" word_end() _ counter:lvalue() _ "=" _ from:expression() _ "TO" word_end() _ to:expression() step:(_ "STEP" word_end() _ step:expression() { step })? body:block() "ENDFOR" word_end() {
+        StatementKind::For { counter, from, to, step, body }
+      }
+
+    rule switch_statement() -> StatementKind
+      = "SWITCH" word_end() _ value:expression() statement_separator() cases:switch_case()* "ENDSWITCH" word_end() {
+        StatementKind::Switch { value, cases }
+      }
+
+    rule switch_case() -> SwitchCase
+      = start:position!() value:switch_label() end:position!() body:block() {
+        SwitchCase { value, body, span: source.span(start, end) }
+      }
+
+    rule switch_label() -> Option<Expression>
+      = "CASE" word_end() _ value:expression() { Some(value) }
+      / "DEFAULT" word_end() { None }
+
+    rule return_statement() -> StatementKind
+      = "RETURN" word_end() value:(_ value:expression() { value })? {
+        StatementKind::Return(value)
+      }
+
+    rule variable_declaration() -> StatementKind
+      = !statement_keyword() type_name:identifier() _ name:identifier() _ array_size:struct_field_array_size()? _ value:struct_field_default()? {
+        StatementKind::VariableDeclaration { type_name, name, array_size, value }
+      }
+
+    rule increment_statement() -> StatementKind
+      // `+` is a valid identifier character so `i++` has to be split manually.
+      = name:$(['a'..='z' | 'A'..='Z' | '_'] ['a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '.']*) "++" {
+        StatementKind::Increment(Expression::Identifier(name.to_owned()))
       }
+      / target:lvalue() _ "++" {
+        StatementKind::Increment(target)
+      }
+      / target:lvalue() _ "--" {
+        StatementKind::Decrement(target)
+      }
+
+    rule assignment() -> StatementKind
+      = target:lvalue() _ operator:assignment_operator() _ value:expression() {
+        StatementKind::Assignment { target, operator, value }
+      }
+
+    rule assignment_operator() -> AssignmentOperator
+      = "+=" { AssignmentOperator::Add }
+      / "-=" { AssignmentOperator::Subtract }
+      / "*=" { AssignmentOperator::Multiply }
+      / "/=" { AssignmentOperator::Divide }
+      / "=" { AssignmentOperator::Assign }
+
+    rule call_statement() -> StatementKind
+      = ("CALL" word_end() _)? call:expression() {?
+        match call {
+          Expression::Call { .. } => Ok(StatementKind::Call(call)),
+          _ => Err("call")
+        }
+      }
+
+    /// A variable, optionally indexed or accessing a struct member.
+    rule lvalue() -> Expression
+      = name:identifier() accessors:accessor()* {
+        accessors
+          .into_iter()
+          .fold(Expression::Identifier(name), |target, accessor| accessor.apply(target))
+      }
+
+    rule accessor() -> Accessor
+      = "[" _ index:expression() _ "]" { Accessor::Index(index) }
+      / "." field:any_identifier() { Accessor::Member(field) }
+
+    rule block_end()
+      = ("ENDFUNC" / "ENDPROC" / "ENDSCRIPT" / "ENDIF" / "ELIF" / "ELSE" / "ENDWHILE" / "ENDREPEAT" / "ENDFOR" / "CASE" / "DEFAULT" / "ENDSWITCH") word_end()
+
+    rule statement_keyword()
+      = block_end()
+      / ("IF" / "WHILE" / "REPEAT" / "FOR" / "SWITCH" / "RETURN" / "BREAKLOOP" / "BREAK" / "RELOOP" / "EXIT" / "CALL") word_end()
+
+    rule word_end()
+      = !identifier_char()
 
     rule comment_declaration() -> Declaration
-      = text:comment() {
-        Declaration::Comment(text.to_owned())
+      = start:position!() text:comment() end:position!() {
+        Declaration::Comment(CommentDeclaration { text, span: source.span(start, end) })
       }
 
     rule comments() -> Vec<String>
-      = comments:(comment() ** (one_eol() _)) {
+      = comments:(comment() ** (one_eol() indent())) {
         comments
       }
 
     rule comment() -> String
+      = line_comment()
+      / block_comment()
+
+    rule line_comment() -> String
       = "//" "/"? text:text_until_eol() {
         text
       }
 
+    rule block_comment() -> String
+      = "/*" text:$((!"*/" [_])*) "*/" {
+        block_comment_text(text)
+      }
+
     rule using_declaration() -> Declaration
-      = "USING" _ s:string_literal() {
-        Declaration::Using(s)
+      = start:position!() "USING" _ path:string_literal() end:position!() {
+        Declaration::Using(UsingDeclaration { path, span: source.span(start, end) })
       }
 
     rule const_declaration() -> Declaration
@@ -75,8 +384,28 @@ peg::parser! {
       }
 
     rule const() -> ConstDeclaration
-      = "CONST_" type_name:identifier() _ name:identifier() _ value:expression() _ comment:comment()? {
-        ConstDeclaration { comment, type_name, name, value }
+      = start:position!() "CONST_" type_name:identifier() inline_space() name:identifier() inline_space() value:expression() inline_space() comment:comment()? end:position!() {
+        ConstDeclaration { comment, type_name, name, value, span: source.span(start, end) }
+      }
+
+    rule tweak_declaration() -> Declaration
+      = tweak:tweak() {
+        Declaration::Tweak(tweak)
+      }
+
+    rule tweak() -> TweakDeclaration
+      = start:position!() "TWEAK_" type_name:identifier() inline_space() name:identifier() inline_space() value:expression() inline_space() comment:comment()? end:position!() {
+        TweakDeclaration { comment, type_name, name, value, span: source.span(start, end) }
+      }
+
+    rule global_declaration() -> Declaration
+      = global:global() {
+        Declaration::Global(global)
+      }
+
+    rule global() -> GlobalDeclaration
+      = start:position!() !reserved_word() type_name:identifier() inline_space() name:identifier() inline_space() array_size:struct_field_array_size()? inline_space() default_value:struct_field_default()? inline_space() comment:comment()? end:position!() {
+        GlobalDeclaration { comment, type_name, name, array_size, default_value, span: source.span(start, end) }
       }
 
     rule struct_declaration() -> Declaration
@@ -85,13 +414,20 @@ peg::parser! {
       }
 
     rule struct() -> StructDeclaration
-      = comments:comments() one_eol()? _ "STRUCT" _ name:identifier() eol() fields:(struct_field() ** eol()) eol() "ENDSTRUCT" {
-        StructDeclaration { name, fields, comments }
+      = comments:comments() one_eol()? _ start:position!() "STRUCT" _ name:identifier() eol() fields:(struct_field() ** eol()) eol() "ENDSTRUCT" end:position!() {
+        StructDeclaration { name, fields, comments, span: source.span(start, end) }
       }
 
     rule struct_field() -> StructField
-      = _ comment_a:struct_field_comment()? _ type_name:identifier() _ field_name:identifier() _ size:struct_field_array_size()? _ default:struct_field_default()? _ comment_b:comment()? {
-        StructField { name: field_name, type_name, array_size: size, default_value: default, comment: comment_b.or(comment_a) }
+      = comment_a:struct_field_comment()? inline_space() start:position!() type_name:identifier() inline_space() field_name:identifier() inline_space() size:struct_field_array_size()? inline_space() default:struct_field_default()? inline_space() comment_b:comment()? end:position!() {
+        StructField {
+          name: field_name,
+          type_name,
+          array_size: size,
+          default_value: default,
+          comment: comment_b.or(comment_a),
+          span: source.span(start, end)
+        }
       }
 
     rule struct_field_comment() -> String
@@ -115,19 +451,21 @@ peg::parser! {
       }
 
     rule enum() -> EnumDeclaration
-      = comments:comments() one_eol()? _ "ENUM" content:enum_content() "ENDENUM" {
-        EnumDeclaration { comments, ..content }
+      = comments:comments() one_eol()? _ start:position!() "ENUM" content:enum_content() "ENDENUM" end:position!() {
+        EnumDeclaration { comments, kind: EnumKind::Enum, span: source.span(start, end), ..content }
       }
 
     rule strict_enum() -> EnumDeclaration
-      = comments:comments() one_eol()? _ "STRICT_ENUM" content:enum_content() "ENDENUM" {
-        EnumDeclaration { comments, ..content }
+      = comments:comments() one_eol()? _ start:position!() "STRICT_ENUM" content:enum_content() "ENDENUM" end:position!() {
+        EnumDeclaration { comments, kind: EnumKind::Strict, span: source.span(start, end), ..content }
       }
 
     rule hash_enum() -> EnumDeclaration
-      = comments:comments() one_eol()? _ "HASH_ENUM" content:enum_content() "ENDENUM" {
+      = comments:comments() one_eol()? _ start:position!() "HASH_ENUM" content:enum_content() "ENDENUM" end:position!() {
         EnumDeclaration {
           comments,
+          kind: EnumKind::Hash,
+          span: source.span(start, end),
           values: content.values
             .into_iter()
             .map(|f| EnumField {
@@ -139,8 +477,8 @@ peg::parser! {
       }
 
     rule enum_content() -> EnumDeclaration
-      =  _ name:identifier() _ (comment() / eol())* _ fields:enum_items() _ (comment() / eol())* {
-        EnumDeclaration { name, values: fields, comments: vec![] }
+      = start:position!() _ name:identifier() _ (comment() / eol())* _ fields:enum_items() _ (comment() / eol())* end:position!() {
+        EnumDeclaration { name, kind: EnumKind::Enum, values: fields, comments: vec![], span: source.span(start, end) }
       }
 
     rule enum_items() -> Vec<EnumField>
@@ -153,13 +491,13 @@ peg::parser! {
       / enum_field_without_value()
 
     rule enum_field_without_value() -> EnumField
-      = comment:(i:comment() eol() {i})? name:identifier() {
-        EnumField { comment, name, value: None }
+      = comment:(i:comment() eol() {i})? start:position!() name:identifier() end:position!() {
+        EnumField { comment, name, value: None, span: source.span(start, end) }
       }
 
     rule enum_field_with_value() -> EnumField
-      = comment:(i:comment() eol() {i})? name:identifier() _ "=" _ value:expression() {
-        EnumField { comment, name, value: Some(value) }
+      = comment:(i:comment() eol() {i})? start:position!() name:identifier() _ "=" _ value:expression() end:position!() {
+        EnumField { comment, name, value: Some(value), span: source.span(start, end) }
       }
 
     rule native_declaration() -> Declaration
@@ -168,44 +506,83 @@ peg::parser! {
       }
 
     rule native() -> NativeDeclaration
-      = comments:comments() one_eol()? "NATIVE" _ function:function_definition() _ "=" _ hash:native_hash() {
-        NativeDeclaration { comments: comments, definition: function, native_hash: hash }
+      = comments:comments() one_eol()? start:position!() "NATIVE" _ function:function_definition() _ "=" _ hash:native_hash() end:position!() {
+        NativeDeclaration { comments, definition: function, native_hash: hash, span: source.span(start, end) }
+      }
+
+    rule typedef_declaration() -> Declaration
+      = typedef:typedef() {
+        Declaration::Typedef(typedef)
+      }
+
+    rule typedef() -> TypedefDeclaration
+      = comments:comments() one_eol()? start:position!() "TYPEDEF" _ definition:function_definition() end:position!() {
+        TypedefDeclaration { comments, definition, span: source.span(start, end) }
+      }
+
+    rule forward_declaration() -> Declaration
+      = forward:forward() {
+        Declaration::Forward(forward)
       }
 
+    rule forward() -> ForwardDeclaration
+      = start:position!() "FORWARD" _ kind:forward_kind() _ name:identifier() _ comment()? end:position!() {
+        ForwardDeclaration { kind, name, span: source.span(start, end) }
+      }
+
+    rule forward_kind() -> ForwardKind
+      = "ENUM" { ForwardKind::Enum }
+      / "STRUCT" { ForwardKind::Struct }
+
     rule function_definition() -> FunctionDefinition
       = function_definition_void()
       / function_definition_with_return_type()
 
     rule function_definition_void() -> FunctionDefinition
-      = "DEBUGONLY"? _ "PROC" _ name:identifier() _ params:function_params() {
-        FunctionDefinition { name, return_type: None, params }
+      = start:position!() debug_only:debug_only() _ "PROC" _ name:identifier() _ params:function_params() end:position!() {
+        FunctionDefinition { name, return_type: None, params, is_debug_only: debug_only, span: source.span(start, end) }
       }
 
     rule function_definition_with_return_type() -> FunctionDefinition
-      = "DEBUGONLY"? _ "FUNC" _ return_type:identifier() _ name:identifier() _ params:function_params() {
-        FunctionDefinition { name, return_type: Some(return_type), params}
+      = start:position!() debug_only:debug_only() _ "FUNC" _ return_type:identifier() _ name:identifier() _ params:function_params() end:position!() {
+        FunctionDefinition { name, return_type: Some(return_type), params, is_debug_only: debug_only, span: source.span(start, end) }
+      }
+
+    rule debug_only() -> bool
+      = debug_only:("DEBUGONLY" word_end())? {
+        debug_only.is_some()
       }
 
     rule function_params() -> Vec<FunctionParameter>
-      = "(" _ params:((function_param() / function_param_varargs()) ** (eol()? _ "," _ eol()?)) _ ")" {
+      = "(" param_space() params:((function_param() / function_param_varargs()) ** (param_space() "," param_space())) param_space() ")" {
         params
       }
 
+    /// Parameters can be spread over several lines with block comments between them.
+    rule param_space()
+      = (eol() / block_comment())*
+
     rule function_param_varargs() -> FunctionParameter
-      = varargs:varargs() {
-        FunctionParameter { name: varargs.clone(), type_: FunctionParameterType { base_type: varargs, is_ref: false, is_array: false }, default_value: None }
+      = start:position!() varargs:varargs() end:position!() {
+        FunctionParameter {
+          name: varargs.clone(),
+          type_: FunctionParameterType { base_type: varargs, is_ref: false, is_array: false },
+          default_value: None,
+          span: source.span(start, end)
+        }
       }
 
     rule function_param() -> FunctionParameter
-      = type_name:function_param_type() _ is_ref:("&")? _ name:identifier() _ is_array:("[]")? _ default_value:default_function_param_value()? {
+      = start:position!() type_name:function_param_type() _ is_ref:("&")? _ name:identifier() _ is_array:("[]")? _ default_value:default_function_param_value()? end:position!() {
         FunctionParameter {
-          name: name,
+          name,
           type_: FunctionParameterType {
             is_ref: is_ref.is_some(),
             base_type: type_name,
             is_array: is_array.is_some()
           },
-          default_value
+          default_value,
+          span: source.span(start, end)
         }
       }
 
@@ -221,38 +598,89 @@ peg::parser! {
 
     rule expression() -> Expression
       = precedence! {
+        x:(@) _ "OR" !identifier_char() _ y:@ { Expression::Or(x.into(), y.into()) }
+        --
+        x:(@) _ "AND" !identifier_char() _ y:@ { Expression::And(x.into(), y.into()) }
+        --
+        x:(@) _ ("==" / "=") _ y:@ { Expression::Equal(x.into(), y.into()) }
+        x:(@) _ ("<>" / "!=") _ y:@ { Expression::NotEqual(x.into(), y.into()) }
+        x:(@) _ "<=" _ y:@ { Expression::LessEqual(x.into(), y.into()) }
+        x:(@) _ ">=" _ y:@ { Expression::GreaterEqual(x.into(), y.into()) }
+        x:(@) _ "<" !['<' | '=' | '>'] _ y:@ { Expression::Less(x.into(), y.into()) }
+        x:(@) _ ">" !['>' | '='] _ y:@ { Expression::Greater(x.into(), y.into()) }
+        --
+        x:(@) _ "|" _ y:@ { Expression::BitOr(x.into(), y.into()) }
+        --
+        x:(@) _ "^" _ y:@ { Expression::BitXor(x.into(), y.into()) }
+        --
+        x:(@) _ "&" _ y:@ { Expression::BitAnd(x.into(), y.into()) }
+        --
+        x:(@) _ "<<" _ y:@ { Expression::ShiftLeft(x.into(), y.into()) }
+        x:(@) _ ">>" _ y:@ { Expression::ShiftRight(x.into(), y.into()) }
+        --
         x:(@) _ "+" _ y:@ { Expression::Add(x.into(), y.into()) }
         x:(@) _ "-" _ y:@ { Expression::Subtract(x.into(), y.into()) }
         --
         x:(@) _ "*" _ y:@ { Expression::Multiply(x.into(), y.into()) }
         x:(@) _ "/" _ y:@ { Expression::Divide(x.into(), y.into()) }
+        x:(@) _ "%" _ y:@ { Expression::Modulo(x.into(), y.into()) }
         --
-        x:(@) _ "|" _ y:@ { Expression::BitOr(x.into(), y.into()) }
+        // Negative number literals are parsed as literals rather than negations.
+        "-" !['0'..='9'] _ x:@ { Expression::Negate(x.into()) }
+        "~" _ x:@ { Expression::BitNot(x.into()) }
+        "NOT" !identifier_char() _ x:@ { Expression::Not(x.into()) }
+        --
+        x:@ "[" _ index:expression() _ "]" { Accessor::Index(index).apply(x) }
+        x:@ "." field:any_identifier() { Accessor::Member(field).apply(x) }
         --
         literal:literal() { Expression::Literal(literal) }
+        name:identifier() _ "(" _ args:(expression() ** (_ "," _)) _ ")" { Expression::Call { name, args } }
         identifier:identifier() { Expression::Identifier(identifier) }
         "(" _ expr:expression() _ ")" { Expression::Parentheses(expr.into()) }
       }
 
     rule literal() -> Literal
       = hash_literal()
+      / vector_literal()
       / float_literal()
       / int_literal()
       / bool_literal()
+      / null_literal()
+      / text_literal()
 
     rule hash_literal() -> Literal
       = "HASH(" _ string:string_literal() _ ")" {
         Literal::Hash(string)
       }
 
+    rule vector_literal() -> Literal
+      = start:position!() "<<" _ x:expression() _ "," _ y:expression() _ "," _ z:expression() _ ">>" end:position!() {
+        Literal::Vector {
+          x: x.into(),
+          y: y.into(),
+          z: z.into(),
+          text: source.contents[start..end].to_owned()
+        }
+      }
+
+    rule null_literal() -> Literal
+      = "NULL" !identifier_char() {
+        Literal::Null
+      }
+
+    rule text_literal() -> Literal
+      = string:string_literal() {
+        Literal::String(string)
+      }
+
     rule float_literal() -> Literal
       = f:float() {
         Literal::Float(f)
       }
 
     rule float() -> f32
-      = quiet!{ n:$(['-']?['0'..='9']+(['.']['0'..='9']+)?) {
-        ? n.parse().or(Err("i32"))
+      = quiet!{ n:$(['-']?['0'..='9']+['.']['0'..='9']+) {
+        ? n.parse().or(Err("f32"))
       }}
       / expected!("float")
 
@@ -276,16 +704,21 @@ peg::parser! {
       }
 
     rule int_literal() -> Literal
-      = i:integer() {
-        Literal::Int(i)
+      = text:$(integer()) {
+        ? parse_integer(text).map(|value| Literal::Int { value, text: text.to_owned() })
       }
 
-    rule integer() -> i32
-      = quiet!{ n:$(['-']?['0'..='9']+) {
-        ? n.parse().or(Err("i32"))
-      }}
+    rule integer()
+      // Only letters, digits and `_` may not follow, `+` and `.` still can.
+      = quiet!{ ['-']? (hex_integer() / binary_integer() / ['0'..='9']+) !['a'..='z' | 'A'..='Z' | '0'..='9' | '_'] }
       / expected!("integer")
 
+    rule hex_integer()
+      = ("0x" / "0X") ['0'..='9' | 'a'..='f' | 'A'..='F']+
+
+    rule binary_integer()
+      = ("0b" / "0B") ['0' | '1']+
+
     rule varargs() -> String
       = quiet!{ s:$("VARARGS" ['0'..='9']?) {
         s.to_owned()
@@ -312,18 +745,28 @@ peg::parser! {
       }
 
     rule any_identifier() -> String
-      = quiet!{ n:$(['a'..='z' | 'A'..='Z' | '_']['a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '!' | '.' | '+']*) {
+      = quiet!{ n:$(['a'..='z' | 'A'..='Z' | '_'] identifier_char()*) {
         n.to_owned()
       }}
       / expected!("identifier")
 
+    rule identifier_char()
+      = ['a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '!' | '.' | '+']
+
     rule string_literal() -> String
       = quiet!{"\"" n:$([^'"']*) "\"" {
         n.to_owned()
       }}
       / expected!("string literal")
 
-    rule _() = quiet!{[' ' | '\t']*}
+    rule _() = quiet!{([' ' | '\t'] / block_comment())*}
+
+    rule indent() = quiet!{[' ' | '\t']*}
+
+    /// Like `_`, but leaves a block comment that ends the line to a trailing
+    /// `comment()` so it is kept the same way a `//` comment is.
+    rule inline_space()
+      = quiet!{([' ' | '\t'] / !(block_comment() indent() (one_eol() / eof())) block_comment())*}
 
     rule eol()
       = quiet!{['\r' | '\n' | ' ' | '\t']+}
@@ -337,3 +780,264 @@ peg::parser! {
       = ![_]
   }
 }
+
+enum Accessor {
+  Index(Expression),
+  Member(String)
+}
+
+impl Accessor {
+  fn apply(self, target: Expression) -> Expression {
+    match self {
+      Accessor::Index(index) => Expression::Index(target.into(), index.into()),
+      Accessor::Member(field) => Expression::Member(target.into(), field)
+    }
+  }
+}
+
+pub struct SkippedDeclaration {
+  pub span:  Span,
+  pub error: Option<ParseError<LineCol>>
+}
+
+pub struct ParseOutput {
+  pub declarations: Vec<Declaration>,
+  pub skipped:      Vec<SkippedDeclaration>
+}
+
+/// Parses a sch file, skipping over declarations that fail to parse instead
+/// of rejecting the whole file.
+pub fn parse_sch(contents: &str, source: &SourceFile) -> ParseOutput {
+  if let Ok(declarations) = sch_parser::sch(contents, source) {
+    return ParseOutput {
+      declarations,
+      skipped: vec![]
+    };
+  }
+
+  let mut output = ParseOutput {
+    declarations: vec![],
+    skipped:      vec![]
+  };
+
+  // `sch_recovering` can skip any input so it never fails.
+  for item in sch_parser::sch_recovering(contents, source).unwrap_or_default() {
+    match item {
+      Ok(declaration) => output.declarations.push(declaration),
+      Err(span) => {
+        let error = skipped_declaration_error(contents, source, &span);
+        output.skipped.push(SkippedDeclaration { span, error });
+      }
+    }
+  }
+
+  output
+}
+
+/// Re-parses a skipped region on its own to find out why it was rejected,
+/// translating the error location back to the position in the whole file.
+///
+/// Returns `None` when the region parses by itself, which happens when the
+/// declaration is followed by unexpected input on the same line.
+fn skipped_declaration_error(
+  contents: &str,
+  source: &SourceFile,
+  span: &Span
+) -> Option<ParseError<LineCol>> {
+  let text = &contents[span.start.offset..span.end.offset];
+
+  sch_parser::sch(text, &SourceFile::new(&source.name, text))
+    .err()
+    .map(|error| {
+      ParseError {
+        location: source.position(span.start.offset + error.location.offset),
+        expected: error.expected
+      }
+    })
+}
+
+/// Parses a decimal, `0x` hexadecimal or `0b` binary integer literal.
+///
+/// Hexadecimal and binary literals may use all 64 bits so masks such as
+/// `0xFFFFFFFFFFFFFFFF` keep their bit pattern.
+fn parse_integer(text: &str) -> Result<i64, &'static str> {
+  let (negative, digits) = match text.strip_prefix('-') {
+    Some(digits) => (true, digits),
+    None => (false, text)
+  };
+
+  let value = if let Some(hex) = digits
+    .strip_prefix("0x")
+    .or_else(|| digits.strip_prefix("0X"))
+  {
+    u64::from_str_radix(hex, 16).map(|v| v as i64)
+  } else if let Some(binary) = digits
+    .strip_prefix("0b")
+    .or_else(|| digits.strip_prefix("0B"))
+  {
+    u64::from_str_radix(binary, 2).map(|v| v as i64)
+  } else {
+    digits.parse::<i64>()
+  }
+  .or(Err("64-bit integer"))?;
+
+  Ok(if negative {
+    value.wrapping_neg()
+  } else {
+    value
+  })
+}
+
+/// Strips the leading `*` decoration and surrounding blank lines from the
+/// contents of a `/* ... */` comment.
+fn block_comment_text(text: &str) -> String {
+  let lines = text
+    .lines()
+    .map(|line| {
+      let trimmed = line.trim_start();
+      trimmed.strip_prefix('*').unwrap_or(trimmed).trim()
+    })
+    .collect::<Vec<_>>();
+
+  let start = lines
+    .iter()
+    .position(|l| !l.is_empty())
+    .unwrap_or(lines.len());
+  let end = lines
+    .iter()
+    .rposition(|l| !l.is_empty())
+    .map_or(start, |i| i + 1);
+
+  lines[start..end].join("\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{
+    model::{Declaration, Statement, StatementKind},
+    parse_sch, ParseOutput, SourceFile
+  };
+
+  fn parse(contents: &str) -> ParseOutput {
+    let output = parse_sch(contents, &SourceFile::new("test.sch", contents));
+    assert!(output.skipped.is_empty(), "declarations were skipped");
+
+    output
+  }
+
+  fn const_value(contents: &str) -> String {
+    match parse(contents).declarations.as_slice() {
+      [Declaration::Const(const_decl)] => const_decl.value.to_string(),
+      declarations => panic!("expected a single constant, got {declarations:?}")
+    }
+  }
+
+  #[test]
+  fn vector_literal_keeps_its_spelling() {
+    assert_eq!(
+      const_value("CONST_VECTOR V <<0.0, -1.0,2.50>>"),
+      "<<0.0, -1.0,2.50>>"
+    );
+  }
+
+  #[test]
+  fn integer_followed_by_operator_without_spaces() {
+    assert_eq!(const_value("CONST_INT X 1+2"), "1 + 2");
+    assert_eq!(const_value("CONST_INT X 0xFF+1"), "0xFF + 1");
+    // `+` is an identifier character, so `x+1` stays a single name here.
+    assert_eq!(const_value("CONST_INT X 2*x+1"), "2 * x+1");
+  }
+
+  fn function_body(contents: &str) -> Vec<Statement> {
+    match parse(contents).declarations.pop() {
+      Some(Declaration::Function(function)) => function.body,
+      declaration => panic!("expected a function, got {declaration:?}")
+    }
+  }
+
+  #[test]
+  fn multi_line_condition_only_affects_its_own_block() {
+    let body = function_body(
+      "FUNC BOOL IS_PED_INJURED_OR_DEAD(PED_INDEX ped)\n  IF IS_PED_INJURED(ped)\n    RETURN \
+       TRUE\n  ENDIF\n  IF (IS_PED_DEAD(ped)\n    OR IS_PED_FATALLY_INJURED(ped))\n    IF \
+       IS_PED_DEAD(ped)\n      RETURN TRUE\n    ENDIF\n  ELSE\n    RETURN FALSE\n  ENDIF\n  \
+       RETURN FALSE\nENDFUNC\n"
+    );
+
+    assert_eq!(body.len(), 3);
+    assert!(matches!(body[0].kind, StatementKind::If { .. }));
+    assert!(matches!(body[2].kind, StatementKind::Return(Some(_))));
+
+    let StatementKind::UnknownBlock { header, body } = &body[1].kind else {
+      panic!("expected an unknown block, got {:?}", body[1].kind);
+    };
+
+    assert_eq!(header, "IF (IS_PED_DEAD(ped)");
+    assert!(
+      matches!(&body[0].kind, StatementKind::Unknown(text) if text == "OR IS_PED_FATALLY_INJURED(ped))")
+    );
+    assert!(matches!(body[1].kind, StatementKind::If { .. }));
+    assert!(matches!(&body[2].kind, StatementKind::Unknown(text) if text == "ELSE"));
+    assert!(matches!(body[3].kind, StatementKind::Return(Some(_))));
+  }
+
+  #[test]
+  fn stray_block_end_keeps_function_as_raw_lines() {
+    let body = function_body("PROC P()\n  CALL_THING()\n  ENDIF\nENDPROC\n");
+
+    assert!(matches!(body[0].kind, StatementKind::Call(_)));
+    assert!(matches!(&body[1].kind, StatementKind::Unknown(text) if text == "ENDIF"));
+  }
+
+  #[test]
+  fn trailing_block_comment_is_kept_like_line_comment() {
+    let output = parse(
+      "CONST_INT Y 2 /* after */\nTWEAK_INT T 1 /* tweak */\nINT g_iFoo /* global */\nNATIVE \
+       PED_INDEX /* handle */\nSTRUCT S\n  INT a /* trailing */\n  INT b //line\nENDSTRUCT\n"
+    );
+
+    let comments = output
+      .declarations
+      .iter()
+      .flat_map(|decl| {
+        match decl {
+          Declaration::Const(c) => vec![c.comment.clone()],
+          Declaration::Tweak(t) => vec![t.comment.clone()],
+          Declaration::Global(g) => vec![g.comment.clone()],
+          Declaration::NativeType(n) => vec![n.comment.clone()],
+          Declaration::Struct(s) => s.fields.iter().map(|f| f.comment.clone()).collect(),
+          _ => vec![]
+        }
+      })
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      comments,
+      ["after", "tweak", "global", "handle", "trailing", "line"].map(|c| Some(c.to_owned()))
+    );
+  }
+
+  #[test]
+  fn block_comment_between_parameters() {
+    let output = parse(
+      "NATIVE FUNC INT F(INT a,\n  /* second */\n  INT b /* inline */ , /* before */ INT c\n) = \
+       \"0x4EDE34FBADD967A6\"\n"
+    );
+
+    assert!(matches!(
+      output.declarations.as_slice(),
+      [Declaration::Native(native)] if native.definition.params.len() == 3
+    ));
+  }
+
+  #[test]
+  fn integer_followed_by_operator_in_function_body() {
+    let output = parse("FUNC INT F(INT iX)\n  RETURN iX*2+1\nENDFUNC\n");
+
+    assert!(matches!(
+      output.declarations.as_slice(),
+      [Declaration::Function(function)]
+        if matches!(function.body[0].kind, StatementKind::Return(Some(_)))
+    ));
+  }
+}
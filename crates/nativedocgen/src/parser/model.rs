@@ -1,20 +1,45 @@
-use std::fmt::Display;
+use std::{fmt::Display, rc::Rc};
+
+use peg::str::LineCol;
+
+#[derive(Debug, Clone)]
+pub struct Span {
+  pub file:  Rc<str>,
+  pub start: LineCol,
+  pub end:   LineCol
+}
 
 #[derive(Debug)]
 pub enum Literal {
-  Int(i32),
+  Int {
+    value: i64,
+    /// The literal as written, e.g. `0xFF`.
+    text:  String
+  },
   Float(f32),
   Bool(bool),
-  Hash(String)
+  Hash(String),
+  String(String),
+  Null,
+  Vector {
+    x:    Box<Expression>,
+    y:    Box<Expression>,
+    z:    Box<Expression>,
+    /// The literal as written, e.g. `<<0.0, 0.0, 0.0>>`.
+    text: String
+  }
 }
 
 impl Display for Literal {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     match self {
-      Literal::Int(i) => write!(f, "{i}"),
+      Literal::Int { text, .. } => write!(f, "{text}"),
       Literal::Float(float) => write!(f, "{float}"),
       Literal::Bool(b) => write!(f, "{b}"),
-      Literal::Hash(h) => write!(f, "HASH(\"{h}\")")
+      Literal::Hash(h) => write!(f, "HASH(\"{h}\")"),
+      Literal::String(s) => write!(f, "\"{s}\""),
+      Literal::Null => write!(f, "NULL"),
+      Literal::Vector { text, .. } => write!(f, "{text}")
     }
   }
 }
@@ -23,14 +48,24 @@ impl Display for Literal {
 pub struct EnumField {
   pub comment: Option<String>,
   pub name:    String,
-  pub value:   Option<Expression>
+  pub value:   Option<Expression>,
+  pub span:    Span
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnumKind {
+  Enum,
+  Strict,
+  Hash
 }
 
 #[derive(Debug)]
 pub struct EnumDeclaration {
   pub comments: Vec<String>,
+  pub kind:     EnumKind,
   pub name:     String,
-  pub values:   Vec<EnumField>
+  pub values:   Vec<EnumField>,
+  pub span:     Span
 }
 
 #[derive(Debug)]
@@ -39,25 +74,48 @@ pub struct StructField {
   pub type_name:     String,
   pub array_size:    Option<Expression>,
   pub default_value: Option<Expression>,
-  pub comment:       Option<String>
+  pub comment:       Option<String>,
+  pub span:          Span
 }
 
 #[derive(Debug)]
 pub struct StructDeclaration {
   pub comments: Vec<String>,
   pub name:     String,
-  pub fields:   Vec<StructField>
+  pub fields:   Vec<StructField>,
+  pub span:     Span
 }
 
+/// Expressions carry no span of their own, they are located through the
+/// declaration, statement, branch or case they belong to.
 #[derive(Debug)]
 pub enum Expression {
   Literal(Literal),
   Identifier(String),
+  Call { name: String, args: Vec<Expression> },
   Add(Box<Expression>, Box<Expression>),
   Subtract(Box<Expression>, Box<Expression>),
   Multiply(Box<Expression>, Box<Expression>),
   Divide(Box<Expression>, Box<Expression>),
+  Modulo(Box<Expression>, Box<Expression>),
   BitOr(Box<Expression>, Box<Expression>),
+  BitXor(Box<Expression>, Box<Expression>),
+  BitAnd(Box<Expression>, Box<Expression>),
+  ShiftLeft(Box<Expression>, Box<Expression>),
+  ShiftRight(Box<Expression>, Box<Expression>),
+  Equal(Box<Expression>, Box<Expression>),
+  NotEqual(Box<Expression>, Box<Expression>),
+  Less(Box<Expression>, Box<Expression>),
+  LessEqual(Box<Expression>, Box<Expression>),
+  Greater(Box<Expression>, Box<Expression>),
+  GreaterEqual(Box<Expression>, Box<Expression>),
+  And(Box<Expression>, Box<Expression>),
+  Or(Box<Expression>, Box<Expression>),
+  Negate(Box<Expression>),
+  BitNot(Box<Expression>),
+  Not(Box<Expression>),
+  Index(Box<Expression>, Box<Expression>),
+  Member(Box<Expression>, String),
   Parentheses(Box<Expression>)
 }
 
@@ -66,16 +124,91 @@ impl Display for Expression {
     match self {
       Expression::Literal(l) => write!(f, "{l}"),
       Expression::Identifier(i) => write!(f, "{i}"),
+      Expression::Call { name, args } => {
+        let args = args
+          .iter()
+          .map(|a| a.to_string())
+          .collect::<Vec<_>>()
+          .join(", ");
+
+        write!(f, "{name}({args})")
+      }
       Expression::Add(l, r) => write!(f, "{l} + {r}"),
       Expression::Subtract(l, r) => write!(f, "{l} - {r}"),
       Expression::Multiply(l, r) => write!(f, "{l} * {r}"),
       Expression::Divide(l, r) => write!(f, "{l} / {r}"),
+      Expression::Modulo(l, r) => write!(f, "{l} % {r}"),
       Expression::BitOr(l, r) => write!(f, "{l} | {r}"),
+      Expression::BitXor(l, r) => write!(f, "{l} ^ {r}"),
+      Expression::BitAnd(l, r) => write!(f, "{l} & {r}"),
+      Expression::ShiftLeft(l, r) => write!(f, "{l} << {r}"),
+      Expression::ShiftRight(l, r) => write!(f, "{l} >> {r}"),
+      Expression::Equal(l, r) => write!(f, "{l} = {r}"),
+      Expression::NotEqual(l, r) => write!(f, "{l} <> {r}"),
+      Expression::Less(l, r) => write!(f, "{l} < {r}"),
+      Expression::LessEqual(l, r) => write!(f, "{l} <= {r}"),
+      Expression::Greater(l, r) => write!(f, "{l} > {r}"),
+      Expression::GreaterEqual(l, r) => write!(f, "{l} >= {r}"),
+      Expression::And(l, r) => write!(f, "{l} AND {r}"),
+      Expression::Or(l, r) => write!(f, "{l} OR {r}"),
+      Expression::Negate(e) => write!(f, "-{e}"),
+      Expression::BitNot(e) => write!(f, "~{e}"),
+      Expression::Not(e) => write!(f, "NOT {e}"),
+      Expression::Index(e, i) => write!(f, "{e}[{i}]"),
+      Expression::Member(e, m) => write!(f, "{e}.{m}"),
       Expression::Parentheses(e) => write!(f, "({e})")
     }
   }
 }
 
+impl Expression {
+  /// Calls `visit` for this expression and every expression nested in it.
+  pub fn walk<'a>(&'a self, visit: &mut impl FnMut(&'a Expression)) {
+    visit(self);
+
+    match self {
+      Expression::Literal(Literal::Vector { x, y, z, .. }) => {
+        x.walk(visit);
+        y.walk(visit);
+        z.walk(visit);
+      }
+      Expression::Literal(_) | Expression::Identifier(_) => {}
+      Expression::Call { args, .. } => {
+        for arg in args {
+          arg.walk(visit);
+        }
+      }
+      Expression::Add(l, r)
+      | Expression::Subtract(l, r)
+      | Expression::Multiply(l, r)
+      | Expression::Divide(l, r)
+      | Expression::Modulo(l, r)
+      | Expression::BitOr(l, r)
+      | Expression::BitXor(l, r)
+      | Expression::BitAnd(l, r)
+      | Expression::ShiftLeft(l, r)
+      | Expression::ShiftRight(l, r)
+      | Expression::Equal(l, r)
+      | Expression::NotEqual(l, r)
+      | Expression::Less(l, r)
+      | Expression::LessEqual(l, r)
+      | Expression::Greater(l, r)
+      | Expression::GreaterEqual(l, r)
+      | Expression::And(l, r)
+      | Expression::Or(l, r)
+      | Expression::Index(l, r) => {
+        l.walk(visit);
+        r.walk(visit);
+      }
+      Expression::Negate(e)
+      | Expression::BitNot(e)
+      | Expression::Not(e)
+      | Expression::Member(e, _)
+      | Expression::Parentheses(e) => e.walk(visit)
+    }
+  }
+}
+
 #[derive(Debug)]
 pub struct FunctionParameterType {
   pub base_type: String,
@@ -91,8 +224,8 @@ impl From<FunctionParameterType> for String {
       is_array
     }: FunctionParameterType
   ) -> Self {
-    let ref_str = is_ref.then_some("&").unwrap_or("");
-    let array_str = is_array.then_some("[]").unwrap_or("");
+    let ref_str = if is_ref { "&" } else { "" };
+    let array_str = if is_array { "[]" } else { "" };
 
     format!("{base_type}{array_str}{ref_str}")
   }
@@ -102,28 +235,240 @@ impl From<FunctionParameterType> for String {
 pub struct FunctionParameter {
   pub name:          String,
   pub type_:         FunctionParameterType,
-  pub default_value: Option<Expression>
+  pub default_value: Option<Expression>,
+  pub span:          Span
 }
 
 #[derive(Debug)]
 pub struct FunctionDefinition {
-  pub name:        String,
-  pub return_type: Option<String>,
-  pub params:      Vec<FunctionParameter>
+  pub name:          String,
+  pub return_type:   Option<String>,
+  pub params:        Vec<FunctionParameter>,
+  pub is_debug_only: bool,
+  pub span:          Span
+}
+
+#[derive(Debug)]
+pub enum AssignmentOperator {
+  Assign,
+  Add,
+  Subtract,
+  Multiply,
+  Divide
+}
+
+#[derive(Debug)]
+pub struct ConditionalBranch {
+  pub condition: Expression,
+  pub body:      Vec<Statement>,
+  /// The `IF`/`ELIF` line, excluding the body.
+  pub span:      Span
+}
+
+#[derive(Debug)]
+pub struct SwitchCase {
+  /// `None` for the `DEFAULT` case.
+  pub value: Option<Expression>,
+  pub body:  Vec<Statement>,
+  pub span:  Span
+}
+
+#[derive(Debug)]
+pub enum StatementKind {
+  VariableDeclaration {
+    type_name:  String,
+    name:       String,
+    array_size: Option<Expression>,
+    value:      Option<Expression>
+  },
+  Assignment {
+    target:   Expression,
+    operator: AssignmentOperator,
+    value:    Expression
+  },
+  Increment(Expression),
+  Decrement(Expression),
+  Call(Expression),
+  If {
+    branches:  Vec<ConditionalBranch>,
+    else_body: Option<Vec<Statement>>
+  },
+  While {
+    condition: Expression,
+    body:      Vec<Statement>
+  },
+  Repeat {
+    count:   Expression,
+    counter: Expression,
+    body:    Vec<Statement>
+  },
+  For {
+    counter: Expression,
+    from:    Expression,
+    to:      Expression,
+    step:    Option<Expression>,
+    body:    Vec<Statement>
+  },
+  Switch {
+    value: Expression,
+    cases: Vec<SwitchCase>
+  },
+  Return(Option<Expression>),
+  Break,
+  BreakLoop,
+  Reloop,
+  Exit,
+  /// A line the statement grammar doesn't understand, kept verbatim.
+  Unknown(String),
+  /// A block statement whose header line couldn't be parsed. `body` holds
+  /// the statements up to the matching end keyword, `ELIF`/`ELSE`/`CASE`
+  /// lines included as `Unknown`.
+  UnknownBlock {
+    header: String,
+    body:   Vec<Statement>
+  }
+}
+
+#[derive(Debug)]
+pub struct Statement {
+  pub kind: StatementKind,
+  pub span: Span
+}
+
+impl Statement {
+  /// Calls `visit` for this statement and every statement nested in it.
+  pub fn walk<'a>(&'a self, visit: &mut impl FnMut(&'a Statement)) {
+    visit(self);
+
+    let bodies: Vec<&[Statement]> = match &self.kind {
+      StatementKind::If {
+        branches,
+        else_body
+      } => {
+        branches
+          .iter()
+          .map(|b| b.body.as_slice())
+          .chain(else_body.as_deref())
+          .collect()
+      }
+      StatementKind::While { body, .. }
+      | StatementKind::Repeat { body, .. }
+      | StatementKind::For { body, .. }
+      | StatementKind::UnknownBlock { body, .. } => vec![body],
+      StatementKind::Switch { cases, .. } => cases.iter().map(|c| c.body.as_slice()).collect(),
+      _ => vec![]
+    };
+
+    for statement in bodies.into_iter().flatten() {
+      statement.walk(visit);
+    }
+  }
+
+  /// The expressions that belong to this statement itself, excluding those of
+  /// nested statements, each with the span of the line it is written on.
+  pub fn expressions<'a>(&'a self) -> Vec<(&'a Expression, &'a Span)> {
+    let own = |expressions: Vec<&'a Expression>| {
+      expressions
+        .into_iter()
+        .map(|e| (e, &self.span))
+        .collect::<Vec<_>>()
+    };
+
+    match &self.kind {
+      StatementKind::VariableDeclaration {
+        array_size, value, ..
+      } => own(array_size.iter().chain(value).collect()),
+      StatementKind::Assignment { target, value, .. } => own(vec![target, value]),
+      StatementKind::Increment(e) | StatementKind::Decrement(e) | StatementKind::Call(e) => {
+        own(vec![e])
+      }
+      StatementKind::If { branches, .. } => {
+        branches.iter().map(|b| (&b.condition, &b.span)).collect()
+      }
+      StatementKind::While { condition, .. } => own(vec![condition]),
+      StatementKind::Repeat { count, counter, .. } => own(vec![count, counter]),
+      StatementKind::For {
+        counter,
+        from,
+        to,
+        step,
+        ..
+      } => own([counter, from, to].into_iter().chain(step).collect()),
+      StatementKind::Switch { value, cases } => {
+        std::iter::once((value, &self.span))
+          .chain(
+            cases
+              .iter()
+              .filter_map(|c| c.value.as_ref().map(|v| (v, &c.span)))
+          )
+          .collect()
+      }
+      StatementKind::Return(value) => own(value.iter().collect()),
+      StatementKind::Break
+      | StatementKind::BreakLoop
+      | StatementKind::Reloop
+      | StatementKind::Exit
+      | StatementKind::Unknown(_)
+      | StatementKind::UnknownBlock { .. } => vec![]
+    }
+  }
 }
 
 #[derive(Debug)]
 pub struct FunctionDeclaration {
   pub comments:   Vec<String>,
   pub definition: FunctionDefinition,
-  pub body:       String
+  pub body:       Vec<Statement>,
+  pub span:       Span
+}
+
+#[derive(Debug)]
+pub struct ScriptDeclaration {
+  #[allow(dead_code)]
+  pub name:   Option<String>,
+  #[allow(dead_code)]
+  pub params: Vec<FunctionParameter>,
+  #[allow(dead_code)]
+  pub body:   Vec<Statement>,
+  #[allow(dead_code)]
+  pub span:   Span
 }
 
 #[derive(Debug)]
 pub struct NativeDeclaration {
   pub comments:    Vec<String>,
   pub definition:  FunctionDefinition,
-  pub native_hash: u64
+  pub native_hash: u64,
+  pub span:        Span
+}
+
+#[derive(Debug)]
+pub struct TypedefDeclaration {
+  pub comments:   Vec<String>,
+  pub definition: FunctionDefinition,
+  pub span:       Span
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForwardKind {
+  Enum,
+  Struct
+}
+
+impl Display for ForwardKind {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ForwardKind::Enum => write!(f, "ENUM"),
+      ForwardKind::Struct => write!(f, "STRUCT")
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct ForwardDeclaration {
+  pub kind: ForwardKind,
+  pub name: String,
+  pub span: Span
 }
 
 #[derive(Debug)]
@@ -131,24 +476,64 @@ pub struct ConstDeclaration {
   pub comment:   Option<String>,
   pub type_name: String,
   pub name:      String,
-  pub value:     Expression
+  pub value:     Expression,
+  pub span:      Span
+}
+
+#[derive(Debug)]
+pub struct TweakDeclaration {
+  pub comment:   Option<String>,
+  pub type_name: String,
+  pub name:      String,
+  pub value:     Expression,
+  pub span:      Span
+}
+
+#[derive(Debug)]
+pub struct GlobalDeclaration {
+  pub comment:       Option<String>,
+  pub type_name:     String,
+  pub name:          String,
+  pub array_size:    Option<Expression>,
+  pub default_value: Option<Expression>,
+  pub span:          Span
 }
 
 #[derive(Debug)]
 pub struct NativeTypeDeclaration {
   pub name:      String,
   pub alias_for: Option<String>,
-  pub comment:   Option<String>
+  pub comment:   Option<String>,
+  pub span:      Span
+}
+
+#[derive(Debug)]
+pub struct CommentDeclaration {
+  #[allow(dead_code)]
+  pub text: String,
+  #[allow(dead_code)]
+  pub span: Span
+}
+
+#[derive(Debug)]
+pub struct UsingDeclaration {
+  pub path: String,
+  pub span: Span
 }
 
 #[derive(Debug)]
 pub enum Declaration {
   Enum(EnumDeclaration),
   Struct(StructDeclaration),
-  Comment(String),
-  Using(String),
+  Comment(#[allow(dead_code)] CommentDeclaration),
+  Using(UsingDeclaration),
   Function(FunctionDeclaration),
+  Script(#[allow(dead_code)] ScriptDeclaration),
   Native(NativeDeclaration),
   NativeType(NativeTypeDeclaration),
-  Const(ConstDeclaration)
+  Typedef(TypedefDeclaration),
+  Forward(ForwardDeclaration),
+  Const(ConstDeclaration),
+  Tweak(TweakDeclaration),
+  Global(GlobalDeclaration)
 }
@@ -0,0 +1,206 @@
+use std::{collections::HashMap, fmt::Display};
+
+pub type Defines = HashMap<String, String>;
+
+#[derive(Debug)]
+pub struct PreprocessError {
+  pub line:    usize,
+  pub message: String
+}
+
+impl Display for PreprocessError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "error at line {}: {}", self.line, self.message)
+  }
+}
+
+impl std::error::Error for PreprocessError {}
+
+peg::parser! {
+  grammar condition_parser(defines: &Defines) for str {
+    pub rule condition() -> bool
+      = _ value:expression() _ {
+        value
+      }
+
+    rule expression() -> bool
+      = precedence! {
+        x:(@) _ "OR" _ y:@ { x || y }
+        --
+        x:(@) _ "AND" _ y:@ { x && y }
+        --
+        "NOT" _ x:@ { !x }
+        --
+        symbol:symbol() { is_symbol_set(defines, symbol) }
+        n:$(['0'..='9']+) { n.chars().any(|c| c != '0') }
+        "(" _ value:expression() _ ")" { value }
+      }
+
+    rule symbol() -> &'input str
+      = quiet!{ !(("AND" / "OR" / "NOT") !['a'..='z' | 'A'..='Z' | '0'..='9' | '_']) s:$(['a'..='z' | 'A'..='Z' | '_']['a'..='z' | 'A'..='Z' | '0'..='9' | '_']*) {
+        s
+      } }
+      / expected!("symbol")
+
+    rule _() = quiet!{[' ' | '\t']*}
+  }
+}
+
+/// A symbol counts as set when it is defined with a value other than `0`.
+fn is_symbol_set(defines: &Defines, symbol: &str) -> bool {
+  defines
+    .get(symbol)
+    .map(|value| value.trim() != "0")
+    .unwrap_or(false)
+}
+
+struct ConditionalBlock {
+  /// Whether the enclosing block is active.
+  parent_active: bool,
+  /// Whether one of the branches of this block has been taken already.
+  taken:         bool,
+  active:        bool,
+  has_else:      bool
+}
+
+/// Evaluates `#IF`, `#IFDEF`, `#IFNDEF`, `#ELSE` and `#ENDIF` directives.
+///
+/// Directive lines and lines in inactive branches are blanked rather than
+/// removed so line numbers of the output match the original file.
+pub fn preprocess(contents: &str, defines: &Defines) -> Result<String, PreprocessError> {
+  let mut output = String::with_capacity(contents.len());
+  let mut blocks: Vec<ConditionalBlock> = vec![];
+
+  for (index, line) in contents.split_inclusive('\n').enumerate() {
+    let line_number = index + 1;
+    let active = blocks.last().is_none_or(|b| b.active);
+    let error = |message: String| {
+      PreprocessError {
+        line: line_number,
+        message
+      }
+    };
+
+    let text = line.trim_end_matches(['\r', '\n']);
+    let ending = &line[text.len()..];
+
+    let Some(directive) = text.trim_start().strip_prefix('#') else {
+      output.push_str(if active { line } else { ending });
+      continue;
+    };
+
+    let (keyword, argument) = directive
+      .split_once([' ', '\t'])
+      .map(|(k, a)| (k, strip_line_comment(a).trim()))
+      .unwrap_or((strip_line_comment(directive).trim(), ""));
+
+    match keyword.to_ascii_uppercase().as_str() {
+      "IF" | "IFDEF" | "IFNDEF" => {
+        if argument.is_empty() {
+          return Err(error(format!("#{keyword} is missing a condition")));
+        }
+
+        let condition = match keyword.to_ascii_uppercase().as_str() {
+          "IFDEF" => defines.contains_key(argument),
+          "IFNDEF" => !defines.contains_key(argument),
+          _ => {
+            condition_parser::condition(argument, defines)
+              .map_err(|e| error(format!("invalid condition `{argument}`: {e}")))?
+          }
+        };
+
+        blocks.push(ConditionalBlock {
+          parent_active: active,
+          taken:         condition,
+          active:        active && condition,
+          has_else:      false
+        });
+      }
+      "ELSE" => {
+        let block = blocks
+          .last_mut()
+          .ok_or_else(|| error("#ELSE without matching #IF".to_owned()))?;
+
+        if block.has_else {
+          return Err(error("duplicate #ELSE".to_owned()));
+        }
+
+        block.has_else = true;
+        block.active = block.parent_active && !block.taken;
+        block.taken = true;
+      }
+      "ENDIF" => {
+        blocks
+          .pop()
+          .ok_or_else(|| error("#ENDIF without matching #IF".to_owned()))?;
+      }
+      _ => {
+        output.push_str(if active { line } else { ending });
+        continue;
+      }
+    }
+
+    output.push_str(ending);
+  }
+
+  if !blocks.is_empty() {
+    return Err(PreprocessError {
+      line:    contents.lines().count(),
+      message: format!("{} unterminated #IF block(s)", blocks.len())
+    });
+  }
+
+  Ok(output)
+}
+
+fn strip_line_comment(text: &str) -> &str {
+  text.split_once("//").map_or(text, |(before, _)| before)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{preprocess, Defines};
+
+  fn run(contents: &str, defines: &[(&str, &str)]) -> String {
+    let defines = defines
+      .iter()
+      .map(|(symbol, value)| (symbol.to_string(), value.to_string()))
+      .collect::<Defines>();
+
+    preprocess(contents, &defines).unwrap()
+  }
+
+  #[test]
+  fn nested_if_in_inactive_branch_stays_inactive() {
+    let contents = "#IF 0\n#IF 1\nA\n#ELSE\nB\n#ENDIF\n#ENDIF\nC\n";
+
+    assert_eq!(run(contents, &[]), "\n\n\n\n\n\n\nC\n");
+  }
+
+  #[test]
+  fn else_after_taken_branch_is_inactive() {
+    let contents = "#IF X\nA\n#ELSE\nB\n#ENDIF\n";
+
+    assert_eq!(run(contents, &[("X", "1")]), "\nA\n\n\n\n");
+    assert_eq!(run(contents, &[]), "\n\n\nB\n\n");
+  }
+
+  #[test]
+  fn ifdef_ignores_value_but_if_does_not() {
+    let defines = [("X", "0")];
+
+    assert_eq!(run("#IFDEF X\nA\n#ENDIF\n", &defines), "\nA\n\n");
+    assert_eq!(run("#IFNDEF X\nA\n#ENDIF\n", &defines), "\n\n\n");
+    assert_eq!(run("#IF X\nA\n#ENDIF\n", &defines), "\n\n\n");
+  }
+
+  #[test]
+  fn blanked_lines_keep_line_numbers() {
+    let contents = "A\r\n#IF 0\r\nB\r\n#ENDIF\r\nC\r\nD";
+    let output = run(contents, &[]);
+
+    assert_eq!(output, "A\r\n\r\n\r\n\r\nC\r\nD");
+    assert_eq!(output.lines().count(), contents.lines().count());
+    assert_eq!(output.lines().position(|l| l == "C"), Some(4));
+  }
+}
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+
+use crate::{
+  diagnostic::Diagnostic,
+  parser::model::{
+    Declaration, EnumKind, Expression, FunctionDefinition, FunctionParameter, Literal, Span
+  }
+};
+
+const BUILTIN_TYPES: &[&str] = &[
+  "INT",
+  "FLOAT",
+  "BOOL",
+  "STRING",
+  "VECTOR",
+  "TEXT_LABEL",
+  "STRUCT",
+  "ENUM",
+  "VARARGS"
+];
+
+#[derive(Debug, Clone, Copy)]
+enum TypeKind {
+  Builtin,
+  NativeType,
+  Enum(EnumKind),
+  Struct,
+  Typedef,
+  Forward
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LiteralKind {
+  Int,
+  Float,
+  Bool,
+  String,
+  Null,
+  Vector,
+  Hash
+}
+
+struct TypeChecker<'a> {
+  types: HashMap<&'a str, TypeKind>
+}
+
+/// Checks that every type used by native, typedef and script function
+/// signatures, struct fields and globals is declared somewhere, and that
+/// literal parameter defaults fit their parameter type.
+pub fn check_types(declarations: &[Declaration], diagnostics: &mut Vec<Diagnostic>) {
+  let mut checker = TypeChecker {
+    types: BUILTIN_TYPES
+      .iter()
+      .map(|name| (*name, TypeKind::Builtin))
+      .collect()
+  };
+
+  for decl in declarations {
+    match decl {
+      Declaration::NativeType(native_type) => {
+        checker.declare(&native_type.name, TypeKind::NativeType)
+      }
+      Declaration::Enum(enum_decl) => {
+        checker.declare(&enum_decl.name, TypeKind::Enum(enum_decl.kind))
+      }
+      Declaration::Struct(struct_decl) => checker.declare(&struct_decl.name, TypeKind::Struct),
+      Declaration::Typedef(typedef) => checker.declare(&typedef.definition.name, TypeKind::Typedef),
+      Declaration::Forward(forward) => checker.declare(&forward.name, TypeKind::Forward),
+      _ => {}
+    }
+  }
+
+  for decl in declarations {
+    match decl {
+      Declaration::Native(native) => checker.check_signature(&native.definition, diagnostics),
+      Declaration::Typedef(typedef) => checker.check_signature(&typedef.definition, diagnostics),
+      Declaration::Function(function) => checker.check_signature(&function.definition, diagnostics),
+      Declaration::Struct(struct_decl) => {
+        for field in &struct_decl.fields {
+          checker.check_type(
+            &field.type_name,
+            &field.span,
+            || format!("field `{}` of {}", field.name, struct_decl.name),
+            diagnostics
+          );
+        }
+      }
+      Declaration::Global(global) => {
+        checker.check_type(
+          &global.type_name,
+          &global.span,
+          || format!("global `{}`", global.name),
+          diagnostics
+        );
+      }
+      _ => {}
+    }
+  }
+}
+
+impl<'a> TypeChecker<'a> {
+  fn declare(&mut self, name: &'a str, kind: TypeKind) {
+    self.types.entry(name).or_insert(kind);
+  }
+
+  fn lookup(&self, type_name: &str) -> Option<TypeKind> {
+    if is_text_label(type_name) || is_varargs(type_name) {
+      return Some(TypeKind::Builtin);
+    }
+
+    self.types.get(type_name).copied()
+  }
+
+  fn check_signature(&self, definition: &FunctionDefinition, diagnostics: &mut Vec<Diagnostic>) {
+    if let Some(return_type) = &definition.return_type {
+      self.check_type(
+        return_type,
+        &definition.span,
+        || format!("return type of {}", definition.name),
+        diagnostics
+      );
+    }
+
+    for param in &definition.params {
+      let known = self.check_type(
+        &param.type_.base_type,
+        &param.span,
+        || format!("parameter `{}` of {}", param.name, definition.name),
+        diagnostics
+      );
+
+      if let Some(kind) = known {
+        self.check_default(param, kind, &definition.name, diagnostics);
+      }
+    }
+  }
+
+  /// Reports `type_name` when it is not declared, returning its kind otherwise.
+  fn check_type(
+    &self,
+    type_name: &str,
+    span: &Span,
+    context: impl FnOnce() -> String,
+    diagnostics: &mut Vec<Diagnostic>
+  ) -> Option<TypeKind> {
+    let kind = self.lookup(type_name);
+
+    if kind.is_none() {
+      let suggestion = self
+        .suggest(type_name)
+        .map(|name| format!(", did you mean `{name}`?"))
+        .unwrap_or_default();
+
+      diagnostics.push(Diagnostic::new(
+        span,
+        format!("unknown type `{type_name}` for {}{suggestion}", context())
+      ));
+    }
+
+    kind
+  }
+
+  fn check_default(
+    &self,
+    param: &FunctionParameter,
+    kind: TypeKind,
+    function: &str,
+    diagnostics: &mut Vec<Diagnostic>
+  ) {
+    let Some(default) = param
+      .default_value
+      .as_ref()
+      .filter(|_| !param.type_.is_array)
+    else {
+      return;
+    };
+
+    let Some(literal) = literal_kind(default) else {
+      return;
+    };
+
+    let Some(accepted) = accepted_literals(&param.type_.base_type, kind) else {
+      return;
+    };
+
+    let fits = accepted.contains(&literal) || (param.type_.is_ref && literal == LiteralKind::Null);
+
+    if !fits {
+      diagnostics.push(Diagnostic::new(
+        &param.span,
+        format!(
+          "default value `{default}` of parameter `{}` of {function} does not fit type {}",
+          param.name, param.type_.base_type
+        )
+      ));
+    }
+  }
+
+  /// The closest declared type name, if one is close enough to be a typo.
+  fn suggest(&self, type_name: &str) -> Option<&'a str> {
+    let wanted = type_name.to_ascii_uppercase();
+    let max_distance = (wanted.len() / 3).max(1);
+
+    self
+      .types
+      .keys()
+      .map(|name| (edit_distance(&wanted, &name.to_ascii_uppercase()), *name))
+      .filter(|(distance, _)| *distance <= max_distance)
+      .min()
+      .map(|(_, name)| name)
+  }
+}
+
+fn is_text_label(type_name: &str) -> bool {
+  type_name
+    .strip_prefix("TEXT_LABEL_")
+    .is_some_and(|size| !size.is_empty() && size.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// `VARARGS` optionally followed by a single digit, matching the parser.
+fn is_varargs(type_name: &str) -> bool {
+  type_name
+    .strip_prefix("VARARGS")
+    .is_some_and(|count| count.len() <= 1 && count.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn literal_kind(expression: &Expression) -> Option<LiteralKind> {
+  match expression {
+    Expression::Literal(literal) => {
+      Some(match literal {
+        Literal::Int { .. } => LiteralKind::Int,
+        Literal::Float(_) => LiteralKind::Float,
+        Literal::Bool(_) => LiteralKind::Bool,
+        Literal::Hash(_) => LiteralKind::Hash,
+        Literal::String(_) => LiteralKind::String,
+        Literal::Null => LiteralKind::Null,
+        Literal::Vector { .. } => LiteralKind::Vector
+      })
+    }
+    Expression::Negate(e) => {
+      literal_kind(e).filter(|kind| matches!(kind, LiteralKind::Int | LiteralKind::Float))
+    }
+    Expression::Parentheses(e) => literal_kind(e),
+    _ => None
+  }
+}
+
+/// The literals a parameter of the given type can default to, `None` when the
+/// type is not checked.
+fn accepted_literals(type_name: &str, kind: TypeKind) -> Option<&'static [LiteralKind]> {
+  use LiteralKind::*;
+
+  match kind {
+    TypeKind::Builtin => {
+      match type_name {
+        "INT" => Some(&[Int, Hash]),
+        "FLOAT" => Some(&[Float, Int]),
+        "BOOL" => Some(&[Bool]),
+        "STRING" => Some(&[String, Null]),
+        "VECTOR" => Some(&[Vector]),
+        _ if is_text_label(type_name) || type_name == "TEXT_LABEL" => Some(&[String]),
+        _ => None
+      }
+    }
+    TypeKind::NativeType => Some(&[Int, Null]),
+    TypeKind::Enum(EnumKind::Enum) => Some(&[Int]),
+    TypeKind::Enum(EnumKind::Strict) => Some(&[]),
+    TypeKind::Enum(EnumKind::Hash) => Some(&[Int, Hash]),
+    TypeKind::Typedef => Some(&[Null]),
+    TypeKind::Struct | TypeKind::Forward => None
+  }
+}
+
+/// Optimal string alignment distance, so swapping two adjacent letters counts
+/// as a single edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+  let a = a.chars().collect::<Vec<_>>();
+  let b = b.chars().collect::<Vec<_>>();
+  let mut before_previous = vec![0; b.len() + 1];
+  let mut previous = (0..=b.len()).collect::<Vec<_>>();
+  let mut current = vec![0; b.len() + 1];
+
+  for i in 0..a.len() {
+    current[0] = i + 1;
+
+    for j in 0..b.len() {
+      let substitution = previous[j] + usize::from(a[i] != b[j]);
+      current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+
+      if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+        current[j + 1] = current[j + 1].min(before_previous[j - 1] + 1);
+      }
+    }
+
+    std::mem::swap(&mut before_previous, &mut previous);
+    std::mem::swap(&mut previous, &mut current);
+  }
+
+  previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::check_types;
+  use crate::parser::{parse_sch, SourceFile};
+
+  fn messages(contents: &str) -> Vec<String> {
+    let output = parse_sch(contents, &SourceFile::new("test.sch", contents));
+    let mut diagnostics = vec![];
+
+    check_types(&output.declarations, &mut diagnostics);
+
+    diagnostics.into_iter().map(|d| d.message).collect()
+  }
+
+  #[test]
+  fn transposed_letters_are_suggested() {
+    let messages = messages("PROC HELPER(FLAOT f)\nENDPROC\n");
+
+    assert_eq!(messages.len(), 1);
+    assert!(
+      messages[0].ends_with("did you mean `FLOAT`?"),
+      "{}",
+      messages[0]
+    );
+  }
+
+  #[test]
+  fn numbered_varargs_are_builtin() {
+    assert_eq!(
+      messages("NATIVE PROC PRINTF(STRING format, VARARGS4) = \"0x4EDE34FBADD967A6\"\n"),
+      Vec::<String>::new()
+    );
+  }
+}
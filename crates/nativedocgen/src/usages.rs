@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use nativedocgen_model::NativeUsage;
+
+use crate::parser::model::{Declaration, Expression, StatementKind};
+
+/// Maps the name of every called function to the script FUNC/PROCs calling it.
+pub fn usage_index(declarations: &[Declaration]) -> HashMap<String, Vec<NativeUsage>> {
+  let mut index: HashMap<String, Vec<NativeUsage>> = HashMap::new();
+
+  let functions = declarations.iter().filter_map(|decl| {
+    match decl {
+      Declaration::Function(function) => Some(function),
+      _ => None
+    }
+  });
+
+  for function in functions {
+    let mut add_usage = |name: &str, file: &str, line: usize| {
+      index.entry(name.to_owned()).or_default().push(NativeUsage {
+        function: function.definition.name.clone(),
+        file: file.to_owned(),
+        line
+      });
+    };
+
+    for statement in &function.body {
+      statement.walk(&mut |statement| {
+        for (expression, span) in statement.expressions() {
+          expression.walk(&mut |expression| {
+            if let Expression::Call { name, .. } = expression {
+              add_usage(name, &span.file, span.start.line);
+            }
+          });
+        }
+
+        // Lines the statement grammar couldn't parse are still searched for calls.
+        if let StatementKind::Unknown(text) | StatementKind::UnknownBlock { header: text, .. } =
+          &statement.kind
+        {
+          for name in called_names(text) {
+            add_usage(name, &statement.span.file, statement.span.start.line);
+          }
+        }
+      });
+    }
+  }
+
+  index
+}
+
+/// Finds every `NAME(` in a line of script text, skipping string literals.
+fn called_names(text: &str) -> Vec<&str> {
+  let mut names = vec![];
+  let mut chars = text.char_indices().peekable();
+
+  while let Some((start, c)) = chars.next() {
+    if c == '"' {
+      chars.by_ref().find(|(_, c)| *c == '"');
+      continue;
+    }
+
+    if !(c.is_ascii_alphabetic() || c == '_') {
+      continue;
+    }
+
+    let mut end = start + 1;
+    while let Some((i, c)) = chars.next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '_') {
+      end = i + c.len_utf8();
+    }
+
+    if text[end..].trim_start().starts_with('(') {
+      names.push(&text[start..end]);
+    }
+  }
+
+  names
+}
+
+#[cfg(test)]
+mod tests {
+  use super::usage_index;
+  use crate::parser::{parse_sch, SourceFile};
+
+  fn usage_lines(contents: &str, name: &str) -> Vec<usize> {
+    let output = parse_sch(contents, &SourceFile::new("test.sch", contents));
+
+    usage_index(&output.declarations)
+      .remove(name)
+      .unwrap_or_default()
+      .into_iter()
+      .map(|usage| usage.line)
+      .collect()
+  }
+
+  #[test]
+  fn calls_in_unparsed_lines_are_found() {
+    let contents = "FUNC BOOL HELPER(PED_INDEX ped)\n  IF IS_PED_INJURED(ped)\n    RETURN \
+                    TRUE\n  ENDIF\n  IF (IS_PED_DEAD(ped)\n    OR IS_PED_DEAD(ped))\n    RETURN \
+                    TRUE\n  ENDIF\n  RETURN FALSE\nENDFUNC\n";
+
+    assert_eq!(usage_lines(contents, "IS_PED_INJURED"), [2]);
+    assert_eq!(usage_lines(contents, "IS_PED_DEAD"), [5, 6]);
+  }
+
+  #[test]
+  fn calls_are_credited_to_their_own_line() {
+    let contents = "PROC HELPER(INT i)\n  IF A()\n    B()\n  ELIF C()\n    B()\n  ENDIF\n  \
+                    SWITCH i\n    CASE D()\n      BREAK\n  ENDSWITCH\nENDPROC\n";
+
+    assert_eq!(usage_lines(contents, "C"), [4]);
+    assert_eq!(usage_lines(contents, "D"), [8]);
+  }
+}